@@ -0,0 +1,288 @@
+//! # Mobile Bindings
+//!
+//! This crate generates Kotlin and Swift bindings for the sum and compare circuits with
+//! [UniFFI](https://mozilla.github.io/uniffi-rs/), from the single interface definition in
+//! `src/trivial_circuits.udl`.
+//!
+//! Build the library for the target platform, then generate the bindings from it:
+//!
+//! ```bash
+//! cargo build -p trivial-circuits-mobile --release
+//! cargo run -p trivial-circuits-mobile --bin uniffi-bindgen -- generate \
+//!     --library target/release/libtrivial_circuits_mobile.so --language kotlin --out-dir out
+//! ```
+
+// The scaffolding generated by UniFFI trips this lint.
+#![allow(clippy::empty_line_after_doc_comments)]
+
+use std::fmt;
+
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::{Proof, ProvingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use trivial_circuits::circuits::compare::{CompareCircuit, PrimeString};
+use trivial_circuits::circuits::container::{self, CircuitId};
+use trivial_circuits::circuits::groth16::{self, Bn254Proof, Groth16Error, SerializationMode};
+use trivial_circuits::circuits::sum::SumCircuit;
+use trivial_circuits::exports::ErrorCode;
+
+uniffi::include_scaffolding!("trivial_circuits");
+
+/// Failures reported by the bindings, matching the `ErrorCode` of the C exports.
+#[derive(Debug, PartialEq)]
+pub enum CircuitError {
+    /// The key bytes could not be deserialized or are malformed.
+    InvalidKey { message: String },
+    /// The proof bytes could not be deserialized.
+    InvalidProof { message: String },
+    /// The private inputs do not satisfy the circuit.
+    InvalidWitness { message: String },
+    /// An unexpected failure, such as a serialization error.
+    Internal { message: String },
+    /// The number of public inputs does not match the verifying key.
+    PublicInputMismatch { message: String },
+    /// An input is malformed, such as bytes that are not a container.
+    InvalidInput { message: String },
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidKey { message } => write!(f, "invalid key: {}", message),
+            Self::InvalidProof { message } => write!(f, "invalid proof: {}", message),
+            Self::InvalidWitness { message } => write!(f, "invalid witness: {}", message),
+            Self::Internal { message } => write!(f, "internal error: {}", message),
+            Self::PublicInputMismatch { message } => {
+                write!(f, "public input mismatch: {}", message)
+            }
+            Self::InvalidInput { message } => write!(f, "invalid input: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
+
+impl CircuitError {
+    /// Wraps `message` in the variant for `code`. Codes the bindings never report, such
+    /// as `Cancelled`, are internal errors.
+    fn new(code: ErrorCode, message: String) -> Self {
+        match code {
+            ErrorCode::InvalidKey => Self::InvalidKey { message },
+            ErrorCode::InvalidProof => Self::InvalidProof { message },
+            ErrorCode::InvalidWitness => Self::InvalidWitness { message },
+            ErrorCode::PublicInputMismatch => Self::PublicInputMismatch { message },
+            ErrorCode::InvalidInput => Self::InvalidInput { message },
+            ErrorCode::Ok
+            | ErrorCode::NullPointer
+            | ErrorCode::Internal
+            | ErrorCode::Panic
+            | ErrorCode::Cancelled => Self::Internal { message },
+        }
+    }
+}
+
+impl From<Groth16Error> for CircuitError {
+    /// Picks the variant for the `ErrorCode` the C exports report for `e`.
+    fn from(e: Groth16Error) -> Self {
+        Self::new(ErrorCode::from(&e), e.to_string())
+    }
+}
+
+/// The serialized keys produced by a setup.
+pub struct KeyPair {
+    pub proving_key: Vec<u8>,
+    pub verifying_key: Vec<u8>,
+}
+
+fn setup_keys<C>(circuit: C, id: CircuitId) -> Result<KeyPair, CircuitError>
+where
+    C: ConstraintSynthesizer<Fr>,
+{
+    let (pk, vk) = groth16::setup::<Bn254, _>(circuit).map_err(|e| CircuitError::Internal {
+        message: e.to_string(),
+    })?;
+    let mode = SerializationMode::UNCOMPRESSED;
+    Ok(KeyPair {
+        proving_key: container::to_bytes(&pk, &id, mode)?,
+        verifying_key: container::to_bytes(&vk, &id, mode)?,
+    })
+}
+
+fn prove<C>(proving_key: Vec<u8>, circuit: C, id: CircuitId) -> Result<Vec<u8>, CircuitError>
+where
+    C: ConstraintSynthesizer<Fr>,
+{
+    let pk = container::from_bytes::<ProvingKey<Bn254>>(&proving_key, &id).map_err(|e| {
+        CircuitError::InvalidKey {
+            message: e.to_string(),
+        }
+    })?;
+    let proof = groth16::generate_proof(pk, circuit)?;
+    Ok(container::to_bytes(
+        proof.as_ref(),
+        &id,
+        SerializationMode::UNCOMPRESSED,
+    )?)
+}
+
+fn verify(
+    verifying_key: Vec<u8>,
+    proof: Vec<u8>,
+    public_input: &[Fr],
+    id: CircuitId,
+) -> Result<bool, CircuitError> {
+    let vk = container::from_bytes(&verifying_key, &id).map_err(|e| CircuitError::InvalidKey {
+        message: e.to_string(),
+    })?;
+    let proof: Bn254Proof = container::from_bytes::<Proof<Bn254>>(&proof, &id)
+        .map_err(|e| CircuitError::InvalidProof {
+            message: e.to_string(),
+        })?
+        .into();
+    Ok(groth16::verify_proof(vk, public_input, proof)?)
+}
+
+pub fn setup_for_sum() -> Result<KeyPair, CircuitError> {
+    setup_keys(SumCircuit::<Fr>::default(), SumCircuit::<Fr>::circuit_id()?)
+}
+
+pub fn generate_proof_for_sum(
+    proving_key: Vec<u8>,
+    a: u64,
+    b: u64,
+    c: u64,
+) -> Result<Vec<u8>, CircuitError> {
+    prove(
+        proving_key,
+        SumCircuit::new(Some(a.into()), Some(b.into()), Some(c.into())),
+        SumCircuit::<Fr>::circuit_id()?,
+    )
+}
+
+pub fn verify_proof_for_sum(
+    verifying_key: Vec<u8>,
+    proof: Vec<u8>,
+    c: u64,
+) -> Result<bool, CircuitError> {
+    verify(
+        verifying_key,
+        proof,
+        &[c.into()],
+        SumCircuit::<Fr>::circuit_id()?,
+    )
+}
+
+pub fn setup_for_compare(prefix_length: u32) -> Result<KeyPair, CircuitError> {
+    let prefix_length = prefix_length as usize;
+    setup_keys(
+        CompareCircuit::<Fr>::with_prefix_length(prefix_length),
+        CompareCircuit::<Fr>::circuit_id(prefix_length)?,
+    )
+}
+
+pub fn generate_proof_for_compare(
+    proving_key: Vec<u8>,
+    prefix: Vec<u8>,
+    full: Vec<u8>,
+) -> Result<Vec<u8>, CircuitError> {
+    let id = CompareCircuit::<Fr>::circuit_id(prefix.len())?;
+    prove(
+        proving_key,
+        CompareCircuit {
+            shorter: Some(PrimeString::<Fr>::from(prefix.as_slice()).into()),
+            larger: Some(PrimeString::<Fr>::from(full.as_slice()).into()),
+        },
+        id,
+    )
+}
+
+pub fn verify_proof_for_compare(
+    verifying_key: Vec<u8>,
+    proof: Vec<u8>,
+    prefix: Vec<u8>,
+) -> Result<bool, CircuitError> {
+    let id = CompareCircuit::<Fr>::circuit_id(prefix.len())?;
+    verify(
+        verifying_key,
+        proof,
+        &Vec::<Fr>::from(PrimeString::<Fr>::from(prefix.as_slice())),
+        id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_relations::r1cs::SynthesisError;
+
+    /// Test that errors are reported as the `ErrorCode` the C exports use for them.
+    #[test]
+    fn errors_match_error_codes() {
+        let name = |e: &CircuitError| match e {
+            CircuitError::InvalidKey { .. } => "InvalidKey",
+            CircuitError::InvalidProof { .. } => "InvalidProof",
+            CircuitError::InvalidWitness { .. } => "InvalidWitness",
+            CircuitError::Internal { .. } => "Internal",
+            CircuitError::PublicInputMismatch { .. } => "PublicInputMismatch",
+            CircuitError::InvalidInput { .. } => "InvalidInput",
+        };
+        let errors = [
+            Groth16Error::Unsatisfied,
+            Groth16Error::Synthesis(SynthesisError::MalformedVerifyingKey),
+            Groth16Error::PublicInputMismatch {
+                expected: 1,
+                actual: 2,
+            },
+            Groth16Error::InvalidContainer,
+            Groth16Error::CircuitMismatch,
+        ];
+        for error in errors {
+            let code = ErrorCode::from(&error);
+            assert_eq!(name(&CircuitError::from(error)), code.name());
+        }
+    }
+
+    #[test]
+    fn prove_verify_sum() {
+        let keys = setup_for_sum().expect("keys created");
+        let proof =
+            generate_proof_for_sum(keys.proving_key.clone(), 10, 32, 42).expect("proof created");
+
+        assert_eq!(
+            verify_proof_for_sum(keys.verifying_key.clone(), proof.clone(), 42),
+            Ok(true)
+        );
+        assert_eq!(
+            verify_proof_for_sum(keys.verifying_key, proof, 43),
+            Ok(false)
+        );
+        assert!(matches!(
+            generate_proof_for_sum(keys.proving_key, 10, 31, 42),
+            Err(CircuitError::InvalidWitness { .. })
+        ));
+    }
+
+    #[test]
+    fn prove_verify_compare() {
+        let keys = setup_for_compare(3).expect("keys created");
+        let proof = generate_proof_for_compare(
+            keys.proving_key.clone(),
+            b"abc".to_vec(),
+            b"abcdef".to_vec(),
+        )
+        .expect("proof created");
+
+        assert_eq!(
+            verify_proof_for_compare(keys.verifying_key.clone(), proof, b"abc".to_vec()),
+            Ok(true)
+        );
+        assert!(matches!(
+            verify_proof_for_compare(keys.verifying_key, Vec::new(), b"abc".to_vec()),
+            Err(CircuitError::InvalidProof { .. })
+        ));
+        assert!(matches!(
+            generate_proof_for_sum(keys.proving_key, 10, 32, 42),
+            Err(CircuitError::InvalidKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,356 @@
+//! # Node.js Bindings
+//!
+//! This crate builds a native Node.js addon with [napi-rs](https://napi.rs/) that verifies
+//! proofs for the sum and compare circuits on the server.
+//!
+//! Keys and proofs are `Buffer`s holding containers from `circuits::container`, and keys
+//! or proofs made for another circuit are rejected with `InvalidKey` or `InvalidProof`. Every
+//! function returns a `Promise` and deserializes and verifies on the libuv thread pool, so
+//! the event loop is never blocked. Failures reject with an `Error` whose `name` and `code`
+//! match the `ErrorCode` of the C exports, such as `InvalidKey` or `InvalidProof`.
+//!
+//! ```js
+//! const { VerifyingKey, verifySumProof } = require("trivial-circuits");
+//!
+//! const valid = await verifySumProof(vkBytes, proofBytes, 42);
+//! const vk = await VerifyingKey.fromBytes(vkBytes);
+//! const alsoValid = await vk.verifySumProof(proofBytes, 42);
+//! ```
+
+use std::sync::Arc;
+
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInt as FieldBigInt, PrimeField};
+use ark_groth16::{PreparedVerifyingKey, Proof};
+use napi::bindgen_prelude::*;
+use napi::{Env, Task};
+use napi_derive::napi;
+use trivial_circuits::circuits::compare::{CompareCircuit, PrimeString};
+use trivial_circuits::circuits::container::{self, CircuitId};
+use trivial_circuits::circuits::groth16::{self, Bn254Proof, Groth16Error};
+use trivial_circuits::circuits::sum::SumCircuit;
+use trivial_circuits::exports::ErrorCode;
+
+/// A failure reported to JavaScript as an `Error` with a matching `name` and `code`.
+pub struct VerifyError {
+    code: &'static str,
+    message: String,
+}
+
+impl VerifyError {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Converts the failure to a JavaScript `Error` that rejects the pending promise.
+    fn into_js(self, env: Env) -> Result<Error> {
+        let mut error = env.create_error(Error::new(Status::GenericFailure, self.message))?;
+        error.set_named_property("name", env.create_string(self.code)?)?;
+        error.set_named_property("code", env.create_string(self.code)?)?;
+        Ok(Error::from(error.into_unknown()))
+    }
+}
+
+impl From<Groth16Error> for VerifyError {
+    fn from(e: Groth16Error) -> Self {
+        Self::new(ErrorCode::from(&e).name(), e.to_string())
+    }
+}
+
+/// Converts a public value to a field element, rejecting negative values and values that
+/// are not smaller than the field modulus.
+fn field_element(value: Either<u32, BigInt>) -> std::result::Result<Fr, VerifyError> {
+    let value = match value {
+        Either::A(value) => return Ok(value.into()),
+        Either::B(value) => value,
+    };
+    let invalid = || VerifyError::new("InvalidInput", "value is not a canonical field element");
+    if value.sign_bit && value.words.iter().any(|&word| word != 0) {
+        return Err(invalid());
+    }
+    if value.words.iter().skip(4).any(|&word| word != 0) {
+        return Err(invalid());
+    }
+
+    let limbs = std::array::from_fn(|i| value.words.get(i).copied().unwrap_or_default());
+    Fr::from_bigint(FieldBigInt::new(limbs)).ok_or_else(invalid)
+}
+
+/// A prepared verifying key and the circuit it was made for.
+pub struct LoadedKey {
+    key: PreparedVerifyingKey<Bn254>,
+    circuit: CircuitId,
+}
+
+fn verifying_key_from_bytes(bytes: &[u8]) -> std::result::Result<LoadedKey, VerifyError> {
+    let (circuit, vk) =
+        container::open(bytes).map_err(|e| VerifyError::new("InvalidKey", e.to_string()))?;
+    Ok(LoadedKey {
+        key: groth16::prepare_verifying_key(&vk),
+        circuit,
+    })
+}
+
+/// Where a verification task finds its verifying key.
+enum KeySource {
+    Bytes(Vec<u8>),
+    Loaded(Arc<LoadedKey>),
+}
+
+/// The circuit a verification task expects the key and proof to be for.
+enum Circuit {
+    Sum,
+    Compare { prefix_length: usize },
+}
+
+impl Circuit {
+    fn id(&self) -> std::result::Result<CircuitId, VerifyError> {
+        Ok(match self {
+            Circuit::Sum => SumCircuit::<Fr>::circuit_id()?,
+            Circuit::Compare { prefix_length } => CompareCircuit::<Fr>::circuit_id(*prefix_length)?,
+        })
+    }
+}
+
+/// Deserializes and verifies a proof on the libuv thread pool.
+pub struct VerifyTask {
+    key: KeySource,
+    circuit: Circuit,
+    proof: Vec<u8>,
+    public_input: std::result::Result<Vec<Fr>, VerifyError>,
+}
+
+impl VerifyTask {
+    fn verify(&mut self) -> std::result::Result<bool, VerifyError> {
+        let public_input = std::mem::replace(&mut self.public_input, Ok(Vec::new()))?;
+        let circuit = self.circuit.id()?;
+        let loaded;
+        let vk = match &self.key {
+            KeySource::Bytes(bytes) => {
+                loaded = verifying_key_from_bytes(bytes)?;
+                &loaded
+            }
+            KeySource::Loaded(vk) => vk.as_ref(),
+        };
+        vk.circuit
+            .check(&circuit)
+            .map_err(|e| VerifyError::new("InvalidKey", e.to_string()))?;
+        let proof: Bn254Proof = container::from_bytes::<Proof<Bn254>>(&self.proof, &circuit)
+            .map_err(|e| VerifyError::new("InvalidProof", e.to_string()))?
+            .into();
+        Ok(groth16::verify_proof_with_prepared_key(
+            &vk.key,
+            &public_input,
+            &proof,
+        )?)
+    }
+}
+
+impl Task for VerifyTask {
+    type Output = std::result::Result<bool, VerifyError>;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(self.verify())
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        output.or_else(|e| Err(e.into_js(env)?))
+    }
+}
+
+/// Deserializes and prepares a verifying key on the libuv thread pool.
+pub struct LoadVerifyingKeyTask {
+    bytes: Vec<u8>,
+}
+
+impl Task for LoadVerifyingKeyTask {
+    type Output = std::result::Result<LoadedKey, VerifyError>;
+    type JsValue = VerifyingKey;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(verifying_key_from_bytes(&self.bytes))
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        match output {
+            Ok(vk) => Ok(VerifyingKey(Arc::new(vk))),
+            Err(e) => Err(e.into_js(env)?),
+        }
+    }
+}
+
+/// A verifying key loaded once, in its prepared form, and reused for many verifications.
+#[napi]
+pub struct VerifyingKey(Arc<LoadedKey>);
+
+#[napi]
+impl VerifyingKey {
+    /// Loads and prepares a serialized verifying key for any circuit.
+    #[napi(ts_return_type = "Promise<VerifyingKey>")]
+    pub fn from_bytes(bytes: Buffer) -> AsyncTask<LoadVerifyingKeyTask> {
+        AsyncTask::new(LoadVerifyingKeyTask {
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Verifies a sum proof against the public value `c`.
+    #[napi(ts_return_type = "Promise<boolean>")]
+    pub fn verify_sum_proof(&self, proof: Buffer, c: Either<u32, BigInt>) -> AsyncTask<VerifyTask> {
+        AsyncTask::new(VerifyTask {
+            key: KeySource::Loaded(self.0.clone()),
+            circuit: Circuit::Sum,
+            proof: proof.to_vec(),
+            public_input: field_element(c).map(|c| vec![c]),
+        })
+    }
+
+    /// Verifies a compare proof against the public `prefix`.
+    #[napi(ts_return_type = "Promise<boolean>")]
+    pub fn verify_compare_proof(&self, proof: Buffer, prefix: Buffer) -> AsyncTask<VerifyTask> {
+        AsyncTask::new(VerifyTask {
+            key: KeySource::Loaded(self.0.clone()),
+            circuit: Circuit::Compare {
+                prefix_length: prefix.len(),
+            },
+            proof: proof.to_vec(),
+            public_input: Ok(PrimeString::<Fr>::from(prefix.as_ref()).into()),
+        })
+    }
+}
+
+/// Verifies a sum proof against the serialized verifying key `vk` and the public value `c`.
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn verify_sum_proof(
+    vk: Buffer,
+    proof: Buffer,
+    c: Either<u32, BigInt>,
+) -> AsyncTask<VerifyTask> {
+    AsyncTask::new(VerifyTask {
+        key: KeySource::Bytes(vk.to_vec()),
+        circuit: Circuit::Sum,
+        proof: proof.to_vec(),
+        public_input: field_element(c).map(|c| vec![c]),
+    })
+}
+
+/// Verifies a compare proof against the serialized verifying key `vk` and the public
+/// `prefix`.
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn verify_compare_proof(vk: Buffer, proof: Buffer, prefix: Buffer) -> AsyncTask<VerifyTask> {
+    AsyncTask::new(VerifyTask {
+        key: KeySource::Bytes(vk.to_vec()),
+        circuit: Circuit::Compare {
+            prefix_length: prefix.len(),
+        },
+        proof: proof.to_vec(),
+        public_input: Ok(PrimeString::<Fr>::from(prefix.as_ref()).into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use trivial_circuits::circuits::groth16::SerializationMode;
+
+    /// Sets up keys for the sum circuit and proves `circuit`, returning the serialized
+    /// verifying key and proof.
+    fn verifying_key_and_proof(circuit: SumCircuit<Fr>, id: &CircuitId) -> (Vec<u8>, Vec<u8>) {
+        let (pk, vk) = groth16::setup::<Bn254, _>(SumCircuit::default()).expect("keys created");
+        let proof = groth16::generate_proof(pk, circuit).expect("proof created");
+        let mode = SerializationMode::UNCOMPRESSED;
+        (
+            container::to_bytes(&vk, id, mode).expect("key serialized"),
+            container::to_bytes(proof.as_ref(), id, mode).expect("proof serialized"),
+        )
+    }
+
+    fn verify(
+        key: KeySource,
+        circuit: Circuit,
+        proof: &[u8],
+        public_input: Vec<Fr>,
+    ) -> std::result::Result<bool, VerifyError> {
+        let mut task = VerifyTask {
+            key,
+            circuit,
+            proof: proof.to_vec(),
+            public_input: Ok(public_input),
+        };
+        task.compute().expect("task computed")
+    }
+
+    #[test]
+    fn verify_task() {
+        let id = SumCircuit::<Fr>::circuit_id().expect("circuit identified");
+        let circuit = SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into()));
+        let (vk, proof) = verifying_key_and_proof(circuit, &id);
+        let mut load = LoadVerifyingKeyTask { bytes: vk.clone() };
+        let loaded = Arc::new(
+            load.compute()
+                .expect("task computed")
+                .ok()
+                .expect("key loaded"),
+        );
+
+        let valid = verify(
+            KeySource::Bytes(vk.clone()),
+            Circuit::Sum,
+            &proof,
+            vec![42.into()],
+        );
+        assert!(matches!(valid, Ok(true)));
+        let valid = verify(
+            KeySource::Loaded(loaded),
+            Circuit::Sum,
+            &proof,
+            vec![43.into()],
+        );
+        assert!(matches!(valid, Ok(false)));
+
+        let e = verify(
+            KeySource::Bytes(vk.clone()),
+            Circuit::Sum,
+            b"",
+            vec![42.into()],
+        );
+        assert_eq!(e.err().map(|e| e.code), Some("InvalidProof"));
+        let e = verify(
+            KeySource::Bytes(vk.clone()),
+            Circuit::Sum,
+            &proof,
+            vec![1.into(), 2.into()],
+        );
+        assert_eq!(e.err().map(|e| e.code), Some("PublicInputMismatch"));
+        let e = verify(
+            KeySource::Bytes(vk),
+            Circuit::Compare { prefix_length: 3 },
+            &proof,
+            vec![42.into()],
+        );
+        assert_eq!(e.err().map(|e| e.code), Some("InvalidKey"));
+    }
+
+    #[test]
+    fn load_verifying_key_task() {
+        let mut load = LoadVerifyingKeyTask {
+            bytes: b"not a key".to_vec(),
+        };
+        let e = load.compute().expect("task computed").err();
+        assert_eq!(e.map(|e| e.code), Some("InvalidKey"));
+    }
+
+    #[test]
+    fn field_elements() {
+        assert!(matches!(field_element(Either::A(42)), Ok(value) if value == Fr::from(42u8)));
+        let negative = BigInt {
+            sign_bit: true,
+            words: vec![1],
+        };
+        let e = field_element(Either::B(negative)).err();
+        assert_eq!(e.map(|e| e.code), Some("InvalidInput"));
+    }
+}
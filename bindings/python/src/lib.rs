@@ -0,0 +1,307 @@
+//! # Python Bindings
+//!
+//! This crate exposes the Groth16 `setup`, `generate_proof` and `verify_proof` functions
+//! for the sum and compare circuits as the `trivial_circuits` Python extension module.
+//!
+//! Keys and proofs are passed as `bytes` holding containers from `circuits::container`,
+//! so they are interchangeable with the ones handled by the C exports, and functions for
+//! one circuit reject keys and proofs made for another.
+//! Failures are raised as subclasses of `trivial_circuits.CircuitError`.
+//!
+//! ```python
+//! import trivial_circuits
+//!
+//! pk, vk = trivial_circuits.setup_for_sum()
+//! proof = trivial_circuits.generate_proof_for_sum(pk, 10, 32, 42)
+//! assert trivial_circuits.verify_proof_for_sum(vk, proof, 42)
+//! ```
+
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInt, PrimeField};
+use ark_groth16::{Proof, ProvingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use num_bigint::BigUint;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use trivial_circuits::circuits::compare::{CompareCircuit, PrimeString};
+use trivial_circuits::circuits::container::{self, CircuitId};
+use trivial_circuits::circuits::groth16::{self, Bn254Proof, Groth16Error, SerializationMode};
+use trivial_circuits::circuits::sum::SumCircuit;
+
+create_exception!(
+    trivial_circuits,
+    CircuitError,
+    PyException,
+    "Base class for errors raised by trivial_circuits."
+);
+create_exception!(
+    trivial_circuits,
+    InvalidKeyError,
+    CircuitError,
+    "The key bytes could not be deserialized or are malformed."
+);
+create_exception!(
+    trivial_circuits,
+    InvalidProofError,
+    CircuitError,
+    "The proof bytes could not be deserialized."
+);
+create_exception!(
+    trivial_circuits,
+    InvalidWitnessError,
+    CircuitError,
+    "The private inputs do not satisfy the circuit."
+);
+create_exception!(
+    trivial_circuits,
+    PublicInputMismatchError,
+    CircuitError,
+    "The number of public inputs does not match the verifying key."
+);
+
+/// Raises `e` as the exception matching its kind.
+fn circuit_error(e: Groth16Error) -> PyErr {
+    let message = e.to_string();
+    match e {
+        Groth16Error::Synthesis(_) | Groth16Error::Unsatisfied | Groth16Error::Cancelled => {
+            InvalidWitnessError::new_err(message)
+        }
+        Groth16Error::PublicInputMismatch { .. } => PublicInputMismatchError::new_err(message),
+        Groth16Error::Serialization(_)
+        | Groth16Error::Deserialization(_)
+        | Groth16Error::NoProofs
+        | Groth16Error::TooManyProofs { .. }
+        | Groth16Error::InvalidAggregationSize
+        | Groth16Error::InvalidAggregationKey
+        | Groth16Error::InvalidContribution { .. }
+        | Groth16Error::CircuitMismatch
+        | Groth16Error::CeremonyPhase
+        | Groth16Error::CircuitTooLarge { .. }
+        | Groth16Error::InvalidPowersOfTau
+        | Groth16Error::InvalidContainer
+        | Groth16Error::ArtifactMismatch { .. } => CircuitError::new_err(message),
+    }
+}
+
+/// Converts a Python integer to a field element, rejecting values that are not smaller
+/// than the field modulus.
+fn field_element(value: BigUint) -> PyResult<Fr> {
+    BigInt::<4>::try_from(value)
+        .ok()
+        .and_then(Fr::from_bigint)
+        .ok_or_else(|| PyValueError::new_err("value is not a canonical field element"))
+}
+
+fn sum_circuit() -> PyResult<CircuitId> {
+    SumCircuit::<Fr>::circuit_id().map_err(circuit_error)
+}
+
+fn compare_circuit(prefix_length: usize) -> PyResult<CircuitId> {
+    CompareCircuit::<Fr>::circuit_id(prefix_length).map_err(circuit_error)
+}
+
+fn setup_keys<'py, C>(
+    py: Python<'py>,
+    circuit: C,
+    id: CircuitId,
+) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)>
+where
+    C: ConstraintSynthesizer<Fr> + Send,
+{
+    let (pk, vk) = py
+        .allow_threads(|| {
+            let (pk, vk) = groth16::setup::<Bn254, _>(circuit)?;
+            let mode = SerializationMode::UNCOMPRESSED;
+            Ok::<_, Groth16Error>((
+                container::to_bytes(&pk, &id, mode)?,
+                container::to_bytes(&vk, &id, mode)?,
+            ))
+        })
+        .map_err(|e| CircuitError::new_err(e.to_string()))?;
+    Ok((PyBytes::new(py, &pk), PyBytes::new(py, &vk)))
+}
+
+fn prove<'py, C>(
+    py: Python<'py>,
+    pk: &[u8],
+    circuit: C,
+    id: CircuitId,
+) -> PyResult<Bound<'py, PyBytes>>
+where
+    C: ConstraintSynthesizer<Fr> + Send,
+{
+    let proof = py.allow_threads(|| {
+        let pk = container::from_bytes::<ProvingKey<Bn254>>(pk, &id)
+            .map_err(|e| InvalidKeyError::new_err(e.to_string()))?;
+        let proof = groth16::generate_proof(pk, circuit).map_err(circuit_error)?;
+        container::to_bytes(proof.as_ref(), &id, SerializationMode::UNCOMPRESSED)
+            .map_err(circuit_error)
+    })?;
+    Ok(PyBytes::new(py, &proof))
+}
+
+fn verify(
+    py: Python<'_>,
+    vk: &[u8],
+    proof: &[u8],
+    public_input: Vec<Fr>,
+    id: CircuitId,
+) -> PyResult<bool> {
+    py.allow_threads(|| {
+        let vk =
+            container::from_bytes(vk, &id).map_err(|e| InvalidKeyError::new_err(e.to_string()))?;
+        let proof: Bn254Proof = container::from_bytes::<Proof<Bn254>>(proof, &id)
+            .map_err(|e| InvalidProofError::new_err(e.to_string()))?
+            .into();
+        groth16::verify_proof(vk, &public_input, proof).map_err(circuit_error)
+    })
+}
+
+/// Generates the proving and verifying keys for the sum circuit.
+///
+/// Returns a `(proving_key, verifying_key)` tuple of `bytes`.
+#[pyfunction]
+fn setup_for_sum(py: Python<'_>) -> PyResult<(Bound<'_, PyBytes>, Bound<'_, PyBytes>)> {
+    setup_keys(py, SumCircuit::<Fr>::default(), sum_circuit()?)
+}
+
+/// Generates a proof that the private values `a` and `b` sum to the public value `c`.
+#[pyfunction]
+fn generate_proof_for_sum<'py>(
+    py: Python<'py>,
+    pk: &[u8],
+    a: BigUint,
+    b: BigUint,
+    c: BigUint,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let circuit = SumCircuit::new(
+        Some(field_element(a)?),
+        Some(field_element(b)?),
+        Some(field_element(c)?),
+    );
+    prove(py, pk, circuit, sum_circuit()?)
+}
+
+/// Verifies a sum proof against the public value `c`.
+#[pyfunction]
+fn verify_proof_for_sum(py: Python<'_>, vk: &[u8], proof: &[u8], c: BigUint) -> PyResult<bool> {
+    verify(py, vk, proof, vec![field_element(c)?], sum_circuit()?)
+}
+
+/// Generates the proving and verifying keys for prefixes of exactly `prefix_length`
+/// bytes.
+///
+/// Returns a `(proving_key, verifying_key)` tuple of `bytes`.
+#[pyfunction]
+fn setup_for_compare(
+    py: Python<'_>,
+    prefix_length: usize,
+) -> PyResult<(Bound<'_, PyBytes>, Bound<'_, PyBytes>)> {
+    setup_keys(
+        py,
+        CompareCircuit::<Fr>::with_prefix_length(prefix_length),
+        compare_circuit(prefix_length)?,
+    )
+}
+
+/// Generates a proof that the private `full` bytes start with the public `prefix`.
+#[pyfunction]
+fn generate_proof_for_compare<'py>(
+    py: Python<'py>,
+    pk: &[u8],
+    prefix: &[u8],
+    full: &[u8],
+) -> PyResult<Bound<'py, PyBytes>> {
+    let circuit = CompareCircuit {
+        shorter: Some(PrimeString::<Fr>::from(prefix).into()),
+        larger: Some(PrimeString::<Fr>::from(full).into()),
+    };
+    prove(py, pk, circuit, compare_circuit(prefix.len())?)
+}
+
+/// Verifies a compare proof against the public `prefix`.
+#[pyfunction]
+fn verify_proof_for_compare(
+    py: Python<'_>,
+    vk: &[u8],
+    proof: &[u8],
+    prefix: &[u8],
+) -> PyResult<bool> {
+    verify(
+        py,
+        vk,
+        proof,
+        PrimeString::<Fr>::from(prefix).into(),
+        compare_circuit(prefix.len())?,
+    )
+}
+
+#[pymodule]
+#[pyo3(name = "trivial_circuits")]
+fn trivial_circuits_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("CircuitError", py.get_type::<CircuitError>())?;
+    m.add("InvalidKeyError", py.get_type::<InvalidKeyError>())?;
+    m.add("InvalidProofError", py.get_type::<InvalidProofError>())?;
+    m.add("InvalidWitnessError", py.get_type::<InvalidWitnessError>())?;
+    m.add(
+        "PublicInputMismatchError",
+        py.get_type::<PublicInputMismatchError>(),
+    )?;
+    m.add_function(wrap_pyfunction!(setup_for_sum, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_proof_for_sum, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_proof_for_sum, m)?)?;
+    m.add_function(wrap_pyfunction!(setup_for_compare, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_proof_for_compare, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_proof_for_compare, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prove_verify_sum() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let (pk, vk) = setup_for_sum(py).expect("keys created");
+            let (pk, vk) = (pk.as_bytes(), vk.as_bytes());
+            let proof = generate_proof_for_sum(py, pk, 10u8.into(), 32u8.into(), 42u8.into())
+                .expect("proof created");
+
+            assert!(verify_proof_for_sum(py, vk, proof.as_bytes(), 42u8.into()).unwrap());
+            assert!(!verify_proof_for_sum(py, vk, proof.as_bytes(), 43u8.into()).unwrap());
+            let err = generate_proof_for_sum(py, pk, 10u8.into(), 31u8.into(), 42u8.into())
+                .expect_err("the witness is wrong");
+            assert!(err.is_instance_of::<InvalidWitnessError>(py));
+            assert!(err.is_instance_of::<CircuitError>(py));
+            let err = verify_proof_for_sum(py, vk, b"", 42u8.into()).expect_err("no proof");
+            assert!(err.is_instance_of::<InvalidProofError>(py));
+        });
+    }
+
+    #[test]
+    fn prove_verify_compare() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let (pk, vk) = setup_for_compare(py, 3).expect("keys created");
+            let (pk, vk) = (pk.as_bytes(), vk.as_bytes());
+            let proof =
+                generate_proof_for_compare(py, pk, b"abc", b"abcdef").expect("proof created");
+
+            assert!(verify_proof_for_compare(py, vk, proof.as_bytes(), b"abc").unwrap());
+            let err = generate_proof_for_compare(py, pk, b"abc", b"abd")
+                .expect_err("the witness is wrong");
+            assert!(err.is_instance_of::<InvalidWitnessError>(py));
+            let err = generate_proof_for_sum(py, pk, 10u8.into(), 32u8.into(), 42u8.into())
+                .expect_err("the key is for another circuit");
+            assert!(err.is_instance_of::<InvalidKeyError>(py));
+            let err = verify_proof_for_sum(py, vk, proof.as_bytes(), 42u8.into())
+                .expect_err("the key is for another circuit");
+            assert!(err.is_instance_of::<InvalidKeyError>(py));
+        });
+    }
+}
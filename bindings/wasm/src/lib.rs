@@ -0,0 +1,264 @@
+//! # WebAssembly Bindings
+//!
+//! This crate exposes the sum and compare circuits to JavaScript through
+//! [wasm-bindgen](https://rustwasm.github.io/wasm-bindgen/), so that proofs can be generated
+//! in the browser without the private inputs leaving the device.
+//!
+//! The functions mirror the C exports in `trivial_circuits::exports`: keys are loaded once
+//! into `ProvingKey` and `VerifyingKey` objects and reused for many proofs. Keys and proofs
+//! are `Uint8Array`s holding containers from `circuits::container`, and keys or proofs made
+//! for another circuit are rejected. Failures are thrown as JavaScript `Error`s whose `name`
+//! matches the `ErrorCode` of the C exports.
+//!
+//! Randomness comes from `thread_rng`, which this crate seeds from the Web Crypto API by
+//! enabling the `js` backend of `getrandom` on wasm32.
+//!
+//! ```bash
+//! wasm-pack build bindings/wasm --target web
+//! ```
+
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::{PreparedVerifyingKey, Proof};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use trivial_circuits::circuits::compare::{CompareCircuit, PrimeString};
+use trivial_circuits::circuits::container::{self, CircuitId};
+use trivial_circuits::circuits::groth16::{self, Bn254Proof, Groth16Error, SerializationMode};
+use trivial_circuits::circuits::sum::SumCircuit;
+use trivial_circuits::exports::{self, ErrorCode};
+use wasm_bindgen::prelude::*;
+
+/// Creates a JavaScript `Error` whose `name` identifies the kind of failure.
+fn error(name: &str, message: impl std::fmt::Display) -> JsValue {
+    let error = js_sys::Error::new(&message.to_string());
+    error.set_name(name);
+    error.into()
+}
+
+/// Creates a JavaScript `Error` named after the `ErrorCode` matching `e`.
+fn groth16_error(e: Groth16Error) -> JsValue {
+    error(ErrorCode::from(&e).name(), e)
+}
+
+/// Reads a field element from its 32-byte little-endian encoding.
+fn field_element(bytes: &[u8]) -> Result<Fr, JsValue> {
+    exports::field_element_from_bytes(bytes).map_err(groth16_error)
+}
+
+fn sum_circuit() -> Result<CircuitId, JsValue> {
+    SumCircuit::<Fr>::circuit_id().map_err(groth16_error)
+}
+
+fn compare_circuit(prefix_length: usize) -> Result<CircuitId, JsValue> {
+    CompareCircuit::<Fr>::circuit_id(prefix_length).map_err(groth16_error)
+}
+
+fn proof_from_bytes(bytes: &[u8], circuit: &CircuitId) -> Result<Bn254Proof, JsValue> {
+    container::from_bytes::<Proof<Bn254>>(bytes, circuit)
+        .map(Bn254Proof::from)
+        .map_err(|e| error("InvalidProof", e))
+}
+
+/// Proves `circuit` with a loaded proving key, returning the serialized proof.
+fn prove<C>(pk: &ProvingKey, circuit: C, id: CircuitId) -> Result<Vec<u8>, JsValue>
+where
+    C: ConstraintSynthesizer<Fr>,
+{
+    pk.circuit.check(&id).map_err(|e| error("InvalidKey", e))?;
+    let proof = groth16::generate_proof_with_key(&pk.key, circuit).map_err(groth16_error)?;
+    container::to_bytes(proof.as_ref(), &id, SerializationMode::UNCOMPRESSED).map_err(groth16_error)
+}
+
+/// Verifies the serialized `proof` against a loaded verifying key.
+fn verify(
+    vk: &VerifyingKey,
+    proof: &[u8],
+    public_input: &[Fr],
+    id: CircuitId,
+) -> Result<bool, JsValue> {
+    vk.circuit.check(&id).map_err(|e| error("InvalidKey", e))?;
+    let proof = proof_from_bytes(proof, &id)?;
+    groth16::verify_proof_with_prepared_key(&vk.key, public_input, &proof).map_err(groth16_error)
+}
+
+fn setup_keys<C>(circuit: C, id: CircuitId) -> Result<KeyPair, JsValue>
+where
+    C: ConstraintSynthesizer<Fr>,
+{
+    let (pk, vk) = groth16::setup::<Bn254, _>(circuit).map_err(|e| error("Internal", e))?;
+    let mode = SerializationMode::UNCOMPRESSED;
+    Ok(KeyPair {
+        proving_key: container::to_bytes(&pk, &id, mode).map_err(groth16_error)?,
+        verifying_key: container::to_bytes(&vk, &id, mode).map_err(groth16_error)?,
+    })
+}
+
+/// The serialized keys produced by a setup.
+#[wasm_bindgen]
+pub struct KeyPair {
+    proving_key: Vec<u8>,
+    verifying_key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl KeyPair {
+    /// The serialized proving key.
+    #[wasm_bindgen(getter, js_name = provingKey)]
+    pub fn proving_key(&self) -> Vec<u8> {
+        self.proving_key.clone()
+    }
+
+    /// The serialized verifying key.
+    #[wasm_bindgen(getter, js_name = verifyingKey)]
+    pub fn verifying_key(&self) -> Vec<u8> {
+        self.verifying_key.clone()
+    }
+}
+
+/// A proving key loaded once and reused for many proofs.
+#[wasm_bindgen]
+pub struct ProvingKey {
+    key: ark_groth16::ProvingKey<Bn254>,
+    circuit: CircuitId,
+}
+
+#[wasm_bindgen]
+impl ProvingKey {
+    /// Loads a serialized proving key for any circuit.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<ProvingKey, JsValue> {
+        let (circuit, key) = container::open(bytes).map_err(|e| error("InvalidKey", e))?;
+        Ok(ProvingKey { key, circuit })
+    }
+}
+
+/// A verifying key loaded once, in its prepared form, and reused for many verifications.
+#[wasm_bindgen]
+pub struct VerifyingKey {
+    key: PreparedVerifyingKey<Bn254>,
+    circuit: CircuitId,
+}
+
+#[wasm_bindgen]
+impl VerifyingKey {
+    /// Loads and prepares a serialized verifying key for any circuit.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<VerifyingKey, JsValue> {
+        let (circuit, vk) = container::open(bytes).map_err(|e| error("InvalidKey", e))?;
+        Ok(VerifyingKey {
+            key: groth16::prepare_verifying_key(&vk),
+            circuit,
+        })
+    }
+}
+
+/// Generates the proving and verifying keys for the sum circuit.
+#[wasm_bindgen(js_name = setupForSum)]
+pub fn setup_for_sum() -> Result<KeyPair, JsValue> {
+    setup_keys(SumCircuit::<Fr>::default(), sum_circuit()?)
+}
+
+/// Generates the proving and verifying keys for prefixes of exactly `prefix_length` bytes.
+#[wasm_bindgen(js_name = setupForCompare)]
+pub fn setup_for_compare(prefix_length: usize) -> Result<KeyPair, JsValue> {
+    setup_keys(
+        CompareCircuit::<Fr>::with_prefix_length(prefix_length),
+        compare_circuit(prefix_length)?,
+    )
+}
+
+/// Generates a proof that the private values `a` and `b` sum to the public value `c`.
+#[wasm_bindgen(js_name = generateProofForSum)]
+pub fn generate_proof_for_sum(pk: &ProvingKey, a: u32, b: u32, c: u32) -> Result<Vec<u8>, JsValue> {
+    prove(
+        pk,
+        SumCircuit::new(Some(a.into()), Some(b.into()), Some(c.into())),
+        sum_circuit()?,
+    )
+}
+
+/// Verifies a sum proof against the public value `c`.
+#[wasm_bindgen(js_name = verifyProofForSum)]
+pub fn verify_proof_for_sum(vk: &VerifyingKey, proof: &[u8], c: u32) -> Result<bool, JsValue> {
+    verify(vk, proof, &[c.into()], sum_circuit()?)
+}
+
+/// Generates a sum proof over 64-bit values, passed as `BigInt`s.
+#[wasm_bindgen(js_name = generateProofForSumU64)]
+pub fn generate_proof_for_sum_u64(
+    pk: &ProvingKey,
+    a: u64,
+    b: u64,
+    c: u64,
+) -> Result<Vec<u8>, JsValue> {
+    prove(
+        pk,
+        SumCircuit::new(Some(a.into()), Some(b.into()), Some(c.into())),
+        sum_circuit()?,
+    )
+}
+
+/// Verifies a sum proof against the 64-bit public value `c`, passed as a `BigInt`.
+#[wasm_bindgen(js_name = verifyProofForSumU64)]
+pub fn verify_proof_for_sum_u64(vk: &VerifyingKey, proof: &[u8], c: u64) -> Result<bool, JsValue> {
+    verify(vk, proof, &[c.into()], sum_circuit()?)
+}
+
+/// Generates a sum proof over field elements given as 32 little-endian bytes each.
+///
+/// Values that are not smaller than the field modulus are rejected rather than reduced.
+#[wasm_bindgen(js_name = generateProofForSumBytes)]
+pub fn generate_proof_for_sum_bytes(
+    pk: &ProvingKey,
+    a: &[u8],
+    b: &[u8],
+    c: &[u8],
+) -> Result<Vec<u8>, JsValue> {
+    let circuit = SumCircuit::new(
+        Some(field_element(a)?),
+        Some(field_element(b)?),
+        Some(field_element(c)?),
+    );
+    prove(pk, circuit, sum_circuit()?)
+}
+
+/// Verifies a sum proof against a field element given as 32 little-endian bytes.
+#[wasm_bindgen(js_name = verifyProofForSumBytes)]
+pub fn verify_proof_for_sum_bytes(
+    vk: &VerifyingKey,
+    proof: &[u8],
+    c: &[u8],
+) -> Result<bool, JsValue> {
+    verify(vk, proof, &[field_element(c)?], sum_circuit()?)
+}
+
+/// Generates a proof that the private `full` bytes start with the public `prefix`.
+#[wasm_bindgen(js_name = generateProofForCompare)]
+pub fn generate_proof_for_compare(
+    pk: &ProvingKey,
+    prefix: &[u8],
+    full: &[u8],
+) -> Result<Vec<u8>, JsValue> {
+    prove(
+        pk,
+        CompareCircuit {
+            shorter: Some(PrimeString::<Fr>::from(prefix).into()),
+            larger: Some(PrimeString::<Fr>::from(full).into()),
+        },
+        compare_circuit(prefix.len())?,
+    )
+}
+
+/// Verifies a compare proof against the public `prefix`.
+#[wasm_bindgen(js_name = verifyProofForCompare)]
+pub fn verify_proof_for_compare(
+    vk: &VerifyingKey,
+    proof: &[u8],
+    prefix: &[u8],
+) -> Result<bool, JsValue> {
+    verify(
+        vk,
+        proof,
+        &Vec::<Fr>::from(PrimeString::<Fr>::from(prefix)),
+        compare_circuit(prefix.len())?,
+    )
+}
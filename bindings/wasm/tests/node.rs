@@ -0,0 +1,51 @@
+//! Tests of the JavaScript-facing functions, run under Node.js with
+//! `wasm-pack test --node bindings/wasm`.
+
+#![cfg(target_arch = "wasm32")]
+
+use trivial_circuits_wasm::*;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// The `name` of a thrown JavaScript `Error`.
+fn error_name(e: JsValue) -> String {
+    e.dyn_into::<js_sys::Error>()
+        .expect("an Error is thrown")
+        .name()
+        .into()
+}
+
+#[wasm_bindgen_test]
+fn prove_verify_sum() {
+    let keys = setup_for_sum().expect("keys created");
+    let pk = ProvingKey::from_bytes(&keys.proving_key()).expect("proving key loaded");
+    let vk = VerifyingKey::from_bytes(&keys.verifying_key()).expect("verifying key loaded");
+    let proof = generate_proof_for_sum(&pk, 10, 32, 42).expect("proof created");
+
+    assert_eq!(verify_proof_for_sum(&vk, &proof, 42), Ok(true));
+    assert_eq!(verify_proof_for_sum(&vk, &proof, 43), Ok(false));
+
+    let mut c = [0u8; 32];
+    c[0] = 42;
+    assert_eq!(verify_proof_for_sum_bytes(&vk, &proof, &c), Ok(true));
+}
+
+#[wasm_bindgen_test]
+fn rejects_invalid_inputs() {
+    let keys = setup_for_compare(3).expect("keys created");
+    let pk = ProvingKey::from_bytes(&keys.proving_key()).expect("proving key loaded");
+    let vk = VerifyingKey::from_bytes(&keys.verifying_key()).expect("verifying key loaded");
+    let proof = generate_proof_for_compare(&pk, b"abc", b"abcdef").expect("proof created");
+
+    assert_eq!(verify_proof_for_compare(&vk, &proof, b"abc"), Ok(true));
+    let e = generate_proof_for_compare(&pk, b"abc", b"abd").expect_err("wrong witness");
+    assert_eq!(error_name(e), "InvalidWitness");
+    let e = generate_proof_for_sum(&pk, 10, 32, 42).expect_err("key for another circuit");
+    assert_eq!(error_name(e), "InvalidKey");
+    let e = verify_proof_for_sum(&vk, &proof, 42).expect_err("key for another circuit");
+    assert_eq!(error_name(e), "InvalidKey");
+    let e = ProvingKey::from_bytes(b"not a key")
+        .err()
+        .expect("not a key");
+    assert_eq!(error_name(e), "InvalidKey");
+}
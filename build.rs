@@ -0,0 +1,29 @@
+//! Generates the C header for the exports in `src/exports.rs`.
+//!
+//! The header is written to `$OUT_DIR/trivial_circuits.h` on every build, so it always
+//! matches the compiled library without touching the source tree. Set
+//! `TRIVIAL_CIRCUITS_HEADER_DIR` to also copy it into a directory of your choice.
+
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("manifest directory"));
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("output directory"));
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("cbindgen configuration");
+
+    let bindings = cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("C header generated");
+    bindings.write_to_file(out_dir.join("trivial_circuits.h"));
+    if let Some(dir) = env::var_os("TRIVIAL_CIRCUITS_HEADER_DIR") {
+        bindings.write_to_file(PathBuf::from(dir).join("trivial_circuits.h"));
+    }
+
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=TRIVIAL_CIRCUITS_HEADER_DIR");
+}
@@ -0,0 +1,812 @@
+//! # Proof Aggregation
+//!
+//! This module aggregates many Groth16 proofs made under the same verifying key into one
+//! proof whose size and verification time grow logarithmically with their number, following
+//! [SnarkPack](https://eprint.iacr.org/2021/529).
+//!
+//! The proofs `(A_i, B_i, C_i)` are committed to with pairing-based commitments, combined
+//! with the powers of a random challenge `r`, and the two resulting inner products are
+//! proven with a generalised inner product argument (GIPA):
+//!
+//! * TIPP shows that `Z = sum r^i e(A_i, B_i)` is the inner pairing product of the
+//!   committed `A` and `B` vectors.
+//! * MIPP shows that `C = sum r^i C_i` is the sum of the committed `C` vector.
+//!
+//! The verifier then checks the Groth16 equation once against `Z` and `C`. The commitment
+//! keys come from an [`AggregationKey`], which embeds the powers of two secrets. Whoever
+//! knows those secrets can forge aggregates, so [`setup_aggregation`] is only suitable when
+//! the party generating the key is trusted; otherwise derive it from two independent
+//! powers-of-tau ceremonies.
+//!
+
+use crate::circuits::groth16::{Groth16Error, Groth16Proof};
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::{AffineRepr, CurveGroup, Group, VariableBaseMSM};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_groth16::{PreparedVerifyingKey, VerifyingKey};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::thread_rng;
+use sha2::{Digest, Sha256};
+
+/// Domain separator of the Fiat-Shamir transcript.
+const TRANSCRIPT_LABEL: &[u8] = b"trivial-circuits/snarkpack/v1";
+
+/// The elements of an [`AggregationKey`] needed to verify aggregate proofs.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregationVerifierKey<E: Pairing> {
+    /// The generator of G1.
+    pub g: E::G1Affine,
+    /// The generator of G2.
+    pub h: E::G2Affine,
+    /// `g^a` for the first secret `a`.
+    pub g_alpha: E::G1Affine,
+    /// `g^b` for the second secret `b`.
+    pub g_beta: E::G1Affine,
+    /// `h^a` for the first secret `a`.
+    pub h_alpha: E::G2Affine,
+    /// `h^b` for the second secret `b`.
+    pub h_beta: E::G2Affine,
+}
+
+/// The structured reference string used to aggregate up to `max_proofs` proofs.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregationKey<E: Pairing> {
+    /// `g^(a^i)` for `i < 2 * max_proofs`.
+    pub g_alpha_powers: Vec<E::G1Affine>,
+    /// `g^(b^i)` for `i < 2 * max_proofs`.
+    pub g_beta_powers: Vec<E::G1Affine>,
+    /// `h^(a^i)` for `i < max_proofs`.
+    pub h_alpha_powers: Vec<E::G2Affine>,
+    /// `h^(b^i)` for `i < max_proofs`.
+    pub h_beta_powers: Vec<E::G2Affine>,
+    /// The elements needed to verify.
+    pub vk: AggregationVerifierKey<E>,
+}
+
+impl<E: Pairing> AggregationKey<E> {
+    /// The largest number of proofs that can be aggregated with this key.
+    pub fn max_proofs(&self) -> usize {
+        self.h_alpha_powers.len()
+    }
+
+    /// Fails with `InvalidAggregationKey` unless there are `2 * max_proofs` powers in G1
+    /// and `max_proofs` in G2 for each secret, as `setup_aggregation` makes them.
+    fn check(&self) -> Result<(), Groth16Error> {
+        let n = self.max_proofs();
+        if n == 0
+            || self.h_beta_powers.len() != n
+            || self.g_alpha_powers.len() != 2 * n
+            || self.g_beta_powers.len() != 2 * n
+        {
+            return Err(Groth16Error::InvalidAggregationKey);
+        }
+        Ok(())
+    }
+}
+
+/// A commitment to a vector, made of one pairing product per secret.
+#[derive(Clone, Copy, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Commitment<E: Pairing> {
+    pub t: PairingOutput<E>,
+    pub u: PairingOutput<E>,
+}
+
+impl<E: Pairing> Commitment<E> {
+    /// Returns `self + left * x + right * x_inv`, the commitment to the folded vectors.
+    fn fold(self, left: &Self, right: &Self, x: E::ScalarField, x_inv: E::ScalarField) -> Self {
+        Self {
+            t: self.t + left.t * x + right.t * x_inv,
+            u: self.u + left.u * x + right.u * x_inv,
+        }
+    }
+}
+
+/// The cross terms sent in one round of the inner product argument.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct GipaRound<E: Pairing> {
+    /// Commitment to the right half of `A` and left half of `B`.
+    pub ab_left: Commitment<E>,
+    /// Commitment to the left half of `A` and right half of `B`.
+    pub ab_right: Commitment<E>,
+    /// Inner pairing product of the right half of `A` and left half of `B`.
+    pub z_ab_left: PairingOutput<E>,
+    /// Inner pairing product of the left half of `A` and right half of `B`.
+    pub z_ab_right: PairingOutput<E>,
+    /// Commitment to the right half of `C`.
+    pub c_left: Commitment<E>,
+    /// Commitment to the left half of `C`.
+    pub c_right: Commitment<E>,
+    /// Sum of the right half of `C` weighted by the left half of the scalars.
+    pub z_c_left: E::G1Affine,
+    /// Sum of the left half of `C` weighted by the right half of the scalars.
+    pub z_c_right: E::G1Affine,
+}
+
+/// A proof that a folded commitment key was derived from the aggregation key.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeyOpening<G: CanonicalSerialize + CanonicalDeserialize> {
+    pub alpha: G,
+    pub beta: G,
+}
+
+/// An aggregate of Groth16 proofs, produced by [`aggregate_proofs`].
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregateProof<E: Pairing> {
+    /// Commitment to the `A` and `B` elements of the proofs.
+    pub com_ab: Commitment<E>,
+    /// Commitment to the `C` elements of the proofs.
+    pub com_c: Commitment<E>,
+    /// `sum r^i e(A_i, B_i)`.
+    pub ip_ab: PairingOutput<E>,
+    /// `sum r^i C_i`.
+    pub agg_c: E::G1Affine,
+    /// One entry per halving of the proof vectors.
+    pub rounds: Vec<GipaRound<E>>,
+    pub final_a: E::G1Affine,
+    pub final_b: E::G2Affine,
+    pub final_c: E::G1Affine,
+    /// The folded commitment key for G1 vectors, one element per secret.
+    pub final_v: KeyOpening<E::G2Affine>,
+    /// The folded commitment key for G2 vectors, one element per secret.
+    pub final_w: KeyOpening<E::G1Affine>,
+    /// Proof that `final_v` was folded from the aggregation key.
+    pub v_opening: KeyOpening<E::G2Affine>,
+    /// Proof that `final_w` was folded from the aggregation key.
+    pub w_opening: KeyOpening<E::G1Affine>,
+}
+
+/// Generates a key for aggregating up to `max_proofs` proofs, rounded up to a power of two.
+///
+/// The secrets are sampled locally and discarded, so the key is only as trustworthy as the
+/// machine that generated it.
+pub fn setup_aggregation<E: Pairing>(max_proofs: usize) -> Result<AggregationKey<E>, Groth16Error> {
+    if max_proofs == 0 {
+        return Err(Groth16Error::InvalidAggregationSize);
+    }
+    let n = max_proofs.next_power_of_two();
+    let mut rng = thread_rng();
+    let alpha = E::ScalarField::rand(&mut rng);
+    let beta = E::ScalarField::rand(&mut rng);
+    let g = E::G1::generator();
+    let h = E::G2::generator();
+
+    let g_alpha_powers = power_table(g, alpha, 2 * n);
+    let g_beta_powers = power_table(g, beta, 2 * n);
+    let h_alpha_powers = power_table(h, alpha, n);
+    let h_beta_powers = power_table(h, beta, n);
+    let vk = AggregationVerifierKey {
+        g: g.into_affine(),
+        h: h.into_affine(),
+        g_alpha: g_alpha_powers[1],
+        g_beta: g_beta_powers[1],
+        h_alpha: (h * alpha).into_affine(),
+        h_beta: (h * beta).into_affine(),
+    };
+
+    Ok(AggregationKey {
+        g_alpha_powers,
+        g_beta_powers,
+        h_alpha_powers,
+        h_beta_powers,
+        vk,
+    })
+}
+
+/// Aggregates proofs made under `vk`, each paired with its public input.
+///
+/// The number of proofs is padded to a power of two by repeating the last one, which
+/// [`verify_aggregate_proof`] mirrors.
+pub fn aggregate_proofs<E: Pairing>(
+    key: &AggregationKey<E>,
+    vk: &VerifyingKey<E>,
+    batch: &[(&[E::ScalarField], &Groth16Proof<E>)],
+) -> Result<AggregateProof<E>, Groth16Error> {
+    key.check()?;
+    let (public_inputs, proofs): (Vec<_>, Vec<_>) = batch.iter().copied().unzip();
+    let public_inputs = pad(&public_inputs)?;
+    let proofs = pad(&proofs)?;
+    let n = proofs.len();
+    if n > key.max_proofs() {
+        return Err(Groth16Error::TooManyProofs {
+            max: key.max_proofs(),
+            actual: batch.len(),
+        });
+    }
+
+    let a = proofs.iter().map(|p| p.0.a).collect::<Vec<_>>();
+    let b = proofs.iter().map(|p| p.0.b).collect::<Vec<_>>();
+    let c = proofs.iter().map(|p| p.0.c).collect::<Vec<_>>();
+    let v_alpha = &key.h_alpha_powers[..n];
+    let v_beta = &key.h_beta_powers[..n];
+    let w_alpha = &key.g_alpha_powers[n..2 * n];
+    let w_beta = &key.g_beta_powers[n..2 * n];
+
+    let com_ab = Commitment {
+        t: E::multi_pairing(&a, v_alpha) + E::multi_pairing(w_alpha, &b),
+        u: E::multi_pairing(&a, v_beta) + E::multi_pairing(w_beta, &b),
+    };
+    let com_c = Commitment {
+        t: E::multi_pairing(&c, v_alpha),
+        u: E::multi_pairing(&c, v_beta),
+    };
+
+    let mut transcript = Transcript::new(n, vk, &public_inputs)?;
+    transcript.append(&com_ab)?;
+    transcript.append(&com_c)?;
+    let r = transcript.challenge::<E::ScalarField>();
+    let r_inv = r.inverse().expect("challenges are nonzero");
+    let r_powers = powers(r, n);
+    let r_inv_powers = powers(r_inv, n);
+
+    // Scaling A and C by r^i and the key for G1 vectors by r^-i leaves the commitments
+    // unchanged, while the inner products become the ones the Groth16 check needs.
+    let mut a = scale::<E::G1Affine>(&a, &r_powers);
+    let mut b = b;
+    let mut c = scale::<E::G1Affine>(&c, &r_powers);
+    let mut scalars = vec![E::ScalarField::one(); n];
+    let mut v_alpha = scale::<E::G2Affine>(v_alpha, &r_inv_powers);
+    let mut v_beta = scale::<E::G2Affine>(v_beta, &r_inv_powers);
+    let mut w_alpha = w_alpha.to_vec();
+    let mut w_beta = w_beta.to_vec();
+
+    let ip_ab = E::multi_pairing(&a, &b);
+    let agg_c = E::G1::msm_unchecked(&c, &scalars).into_affine();
+    transcript.append(&ip_ab)?;
+    transcript.append(&agg_c)?;
+
+    let mut rounds = Vec::new();
+    let mut challenges = Vec::new();
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (c_l, c_r) = c.split_at(half);
+        let (s_l, s_r) = scalars.split_at(half);
+        let (va_l, va_r) = v_alpha.split_at(half);
+        let (vb_l, vb_r) = v_beta.split_at(half);
+        let (wa_l, wa_r) = w_alpha.split_at(half);
+        let (wb_l, wb_r) = w_beta.split_at(half);
+
+        let round = GipaRound {
+            ab_left: Commitment {
+                t: E::multi_pairing(a_r, va_l) + E::multi_pairing(wa_r, b_l),
+                u: E::multi_pairing(a_r, vb_l) + E::multi_pairing(wb_r, b_l),
+            },
+            ab_right: Commitment {
+                t: E::multi_pairing(a_l, va_r) + E::multi_pairing(wa_l, b_r),
+                u: E::multi_pairing(a_l, vb_r) + E::multi_pairing(wb_l, b_r),
+            },
+            z_ab_left: E::multi_pairing(a_r, b_l),
+            z_ab_right: E::multi_pairing(a_l, b_r),
+            c_left: Commitment {
+                t: E::multi_pairing(c_r, va_l),
+                u: E::multi_pairing(c_r, vb_l),
+            },
+            c_right: Commitment {
+                t: E::multi_pairing(c_l, va_r),
+                u: E::multi_pairing(c_l, vb_r),
+            },
+            z_c_left: E::G1::msm_unchecked(c_r, s_l).into_affine(),
+            z_c_right: E::G1::msm_unchecked(c_l, s_r).into_affine(),
+        };
+        transcript.append(&round)?;
+        let x = transcript.challenge::<E::ScalarField>();
+        let x_inv = x.inverse().expect("challenges are nonzero");
+
+        let next_a = fold(a_l, a_r, x);
+        let next_b = fold(b_l, b_r, x_inv);
+        let next_c = fold(c_l, c_r, x);
+        let next_scalars = s_l.iter().zip(s_r).map(|(l, r)| *l + x_inv * r).collect();
+        let next_v_alpha = fold(va_l, va_r, x_inv);
+        let next_v_beta = fold(vb_l, vb_r, x_inv);
+        let next_w_alpha = fold(wa_l, wa_r, x);
+        let next_w_beta = fold(wb_l, wb_r, x);
+        (a, b, c, scalars) = (next_a, next_b, next_c, next_scalars);
+        (v_alpha, v_beta) = (next_v_alpha, next_v_beta);
+        (w_alpha, w_beta) = (next_w_alpha, next_w_beta);
+
+        rounds.push(round);
+        challenges.push(x);
+    }
+
+    let final_v = KeyOpening {
+        alpha: v_alpha[0],
+        beta: v_beta[0],
+    };
+    let final_w = KeyOpening {
+        alpha: w_alpha[0],
+        beta: w_beta[0],
+    };
+    transcript.append(&(a[0], b[0], c[0]))?;
+    transcript.append(&final_v)?;
+    transcript.append(&final_w)?;
+    let z = transcript.challenge::<E::ScalarField>();
+
+    let v_poly = v_key_polynomial(&challenges, r_inv);
+    let w_poly = w_key_polynomial(&challenges, n);
+    let v_quotient = divide_by_linear(&v_poly, z);
+    let w_quotient = divide_by_linear(&w_poly, z);
+    let v_opening = KeyOpening {
+        alpha: E::G2::msm_unchecked(&key.h_alpha_powers, &v_quotient).into_affine(),
+        beta: E::G2::msm_unchecked(&key.h_beta_powers, &v_quotient).into_affine(),
+    };
+    let w_opening = KeyOpening {
+        alpha: E::G1::msm_unchecked(&key.g_alpha_powers, &w_quotient).into_affine(),
+        beta: E::G1::msm_unchecked(&key.g_beta_powers, &w_quotient).into_affine(),
+    };
+
+    Ok(AggregateProof {
+        com_ab,
+        com_c,
+        ip_ab,
+        agg_c,
+        rounds,
+        final_a: a[0],
+        final_b: b[0],
+        final_c: c[0],
+        final_v,
+        final_w,
+        v_opening,
+        w_opening,
+    })
+}
+
+/// Verifies an aggregate of proofs made under the key prepared in `pvk`, given the public
+/// input of every aggregated proof in order.
+pub fn verify_aggregate_proof<E: Pairing>(
+    key: &AggregationVerifierKey<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &[&[E::ScalarField]],
+    proof: &AggregateProof<E>,
+) -> Result<bool, Groth16Error> {
+    let public_inputs = pad(public_inputs)?;
+    let n = public_inputs.len();
+    let expected = pvk
+        .vk
+        .gamma_abc_g1
+        .len()
+        .checked_sub(1)
+        .ok_or(Groth16Error::Synthesis(
+            SynthesisError::MalformedVerifyingKey,
+        ))?;
+    if let Some(input) = public_inputs.iter().find(|input| input.len() != expected) {
+        return Err(Groth16Error::PublicInputMismatch {
+            expected,
+            actual: input.len(),
+        });
+    }
+    // The round count comes from the proof, so compare it without shifting by it.
+    if !n.is_power_of_two() || proof.rounds.len() != n.trailing_zeros() as usize {
+        return Ok(false);
+    }
+
+    let mut transcript = Transcript::new(n, &pvk.vk, &public_inputs)?;
+    transcript.append(&proof.com_ab)?;
+    transcript.append(&proof.com_c)?;
+    let r = transcript.challenge::<E::ScalarField>();
+    let r_inv = r.inverse().expect("challenges are nonzero");
+    transcript.append(&proof.ip_ab)?;
+    transcript.append(&proof.agg_c)?;
+
+    let mut com_ab = proof.com_ab;
+    let mut com_c = proof.com_c;
+    let mut z_ab = proof.ip_ab;
+    let mut z_c = proof.agg_c.into_group();
+    let mut challenges = Vec::new();
+    for round in &proof.rounds {
+        transcript.append(round)?;
+        let x = transcript.challenge::<E::ScalarField>();
+        let x_inv = x.inverse().expect("challenges are nonzero");
+        com_ab = com_ab.fold(&round.ab_left, &round.ab_right, x, x_inv);
+        com_c = com_c.fold(&round.c_left, &round.c_right, x, x_inv);
+        z_ab = z_ab + round.z_ab_left * x + round.z_ab_right * x_inv;
+        z_c += round.z_c_left * x + round.z_c_right * x_inv;
+        challenges.push(x);
+    }
+
+    transcript.append(&(proof.final_a, proof.final_b, proof.final_c))?;
+    transcript.append(&proof.final_v)?;
+    transcript.append(&proof.final_w)?;
+    let z = transcript.challenge::<E::ScalarField>();
+
+    // The folded keys must be the claimed polynomials in the secrets, checked at `z`.
+    let v_at_z = evaluate(&v_key_polynomial(&challenges, r_inv), z);
+    let w_at_z = evaluate(&w_key_polynomial(&challenges, n), z);
+    let g_minus_z = |g_secret: E::G1Affine| (g_secret.into_group() - key.g * z).into_affine();
+    let h_minus_z = |h_secret: E::G2Affine| (h_secret.into_group() - key.h * z).into_affine();
+    let v_openings_hold = [
+        (proof.final_v.alpha, proof.v_opening.alpha, key.g_alpha),
+        (proof.final_v.beta, proof.v_opening.beta, key.g_beta),
+    ]
+    .into_iter()
+    .all(|(final_v, opening, g_secret)| {
+        let value = (final_v.into_group() - key.h * v_at_z).into_affine();
+        E::multi_pairing(
+            [key.g, (-g_minus_z(g_secret).into_group()).into_affine()],
+            [value, opening],
+        )
+        .is_zero()
+    });
+    let w_openings_hold = [
+        (proof.final_w.alpha, proof.w_opening.alpha, key.h_alpha),
+        (proof.final_w.beta, proof.w_opening.beta, key.h_beta),
+    ]
+    .into_iter()
+    .all(|(final_w, opening, h_secret)| {
+        let value = (final_w.into_group() - key.g * w_at_z).into_affine();
+        E::multi_pairing(
+            [value, (-opening.into_group()).into_affine()],
+            [key.h, h_minus_z(h_secret)],
+        )
+        .is_zero()
+    });
+    if !v_openings_hold || !w_openings_hold {
+        return Ok(false);
+    }
+
+    // The folded vectors must open the folded commitments and inner products.
+    let final_scalar = challenges
+        .iter()
+        .map(|x| E::ScalarField::one() + x.inverse().expect("challenges are nonzero"))
+        .product::<E::ScalarField>();
+    let final_com_ab = Commitment {
+        t: E::pairing(proof.final_a, proof.final_v.alpha)
+            + E::pairing(proof.final_w.alpha, proof.final_b),
+        u: E::pairing(proof.final_a, proof.final_v.beta)
+            + E::pairing(proof.final_w.beta, proof.final_b),
+    };
+    let final_com_c = Commitment {
+        t: E::pairing(proof.final_c, proof.final_v.alpha),
+        u: E::pairing(proof.final_c, proof.final_v.beta),
+    };
+    if com_ab != final_com_ab
+        || com_c != final_com_c
+        || z_ab != E::pairing(proof.final_a, proof.final_b)
+        || z_c != proof.final_c * final_scalar
+    {
+        return Ok(false);
+    }
+
+    // Finally, the Groth16 equation combined with the powers of r.
+    let r_powers = powers(r, n);
+    let mut input_scalars = vec![E::ScalarField::zero(); expected + 1];
+    for (input, r_i) in public_inputs.iter().zip(&r_powers) {
+        input_scalars[0] += r_i;
+        for (acc, x) in input_scalars[1..].iter_mut().zip(input.iter()) {
+            *acc += *r_i * x;
+        }
+    }
+    let combined_inputs = E::G1::msm_unchecked(&pvk.vk.gamma_abc_g1, &input_scalars);
+    let r_sum = r_powers.iter().sum::<E::ScalarField>();
+    let rhs = E::multi_miller_loop(
+        [combined_inputs.into_affine(), proof.agg_c],
+        [pvk.gamma_g2_neg_pc.clone(), pvk.delta_g2_neg_pc.clone()],
+    );
+    let Some(rhs) = E::final_exponentiation(rhs) else {
+        return Ok(false);
+    };
+    Ok(proof.ip_ab + rhs == PairingOutput::<E>(pvk.alpha_g1_beta_g2) * r_sum)
+}
+
+/// A Fiat-Shamir transcript hashing everything the prover sends.
+struct Transcript {
+    hasher: Sha256,
+}
+
+impl Transcript {
+    /// Starts a transcript bound to the number of proofs, the verifying key and the public
+    /// inputs, so that the challenges depend on the statement being proven.
+    fn new<E: Pairing>(
+        n: usize,
+        vk: &VerifyingKey<E>,
+        public_inputs: &[&[E::ScalarField]],
+    ) -> Result<Self, Groth16Error> {
+        let mut transcript = Self {
+            hasher: Sha256::new(),
+        };
+        transcript.hasher.update(TRANSCRIPT_LABEL);
+        transcript.append(&(n as u64))?;
+        transcript.append(vk)?;
+        for input in public_inputs {
+            transcript.append(&input.to_vec())?;
+        }
+        Ok(transcript)
+    }
+
+    fn append<T: CanonicalSerialize>(&mut self, value: &T) -> Result<(), Groth16Error> {
+        let mut bytes = Vec::new();
+        value
+            .serialize_compressed(&mut bytes)
+            .map_err(Groth16Error::Serialization)?;
+        self.hasher.update(&bytes);
+        Ok(())
+    }
+
+    /// Derives a nonzero challenge and absorbs it, so the next one differs.
+    fn challenge<F: PrimeField>(&mut self) -> F {
+        loop {
+            let digest = self.hasher.clone().finalize();
+            self.hasher.update(digest);
+            let challenge = F::from_le_bytes_mod_order(&digest);
+            if !challenge.is_zero() {
+                return challenge;
+            }
+        }
+    }
+}
+
+/// Pads `values` to a power of two by repeating the last one.
+fn pad<T: Copy>(values: &[T]) -> Result<Vec<T>, Groth16Error> {
+    let last = *values.last().ok_or(Groth16Error::NoProofs)?;
+    let mut padded = values.to_vec();
+    padded.resize(values.len().next_power_of_two(), last);
+    Ok(padded)
+}
+
+/// Returns `[1, x, x^2, ..., x^(n-1)]`.
+fn powers<F: Field>(x: F, n: usize) -> Vec<F> {
+    std::iter::successors(Some(F::one()), |p| Some(*p * x))
+        .take(n)
+        .collect()
+}
+
+/// Returns `[base^(x^i)]` for `i < n`.
+fn power_table<G: CurveGroup>(base: G, x: G::ScalarField, n: usize) -> Vec<G::Affine> {
+    let table = powers(x, n)
+        .into_iter()
+        .map(|p| base * p)
+        .collect::<Vec<_>>();
+    G::normalize_batch(&table)
+}
+
+fn scale<G: AffineRepr>(points: &[G], scalars: &[G::ScalarField]) -> Vec<G> {
+    let scaled = points
+        .iter()
+        .zip(scalars)
+        .map(|(p, s)| *p * s)
+        .collect::<Vec<_>>();
+    G::Group::normalize_batch(&scaled)
+}
+
+/// Returns `left + right * x`, element by element.
+fn fold<G: AffineRepr>(left: &[G], right: &[G], x: G::ScalarField) -> Vec<G> {
+    let folded = left
+        .iter()
+        .zip(right)
+        .map(|(l, r)| *r * x + l)
+        .collect::<Vec<_>>();
+    G::Group::normalize_batch(&folded)
+}
+
+/// Coefficients of `prod_j (1 + c_j X^(2^j))`, given the `c_j` from the last round to the
+/// first.
+fn product_coefficients<F: Field>(factors: impl Iterator<Item = F>) -> Vec<F> {
+    let mut coefficients = vec![F::one()];
+    for c in factors {
+        let shifted = coefficients.iter().map(|a| *a * c).collect::<Vec<_>>();
+        coefficients.extend(shifted);
+    }
+    coefficients
+}
+
+/// The polynomial in the secret whose evaluation the folded key for G1 vectors encodes.
+///
+/// Round `j` of `k` halves a key scaled by `r^-i` with `x_j^-1`, contributing the factor
+/// `1 + x_j^-1 (X / r)^(2^(k - 1 - j))`.
+fn v_key_polynomial<F: Field>(challenges: &[F], r_inv: F) -> Vec<F> {
+    let mut r_inv_power = r_inv;
+    let factors = challenges.iter().rev().map(|x| {
+        let factor = x.inverse().expect("challenges are nonzero") * r_inv_power;
+        r_inv_power.square_in_place();
+        factor
+    });
+    product_coefficients(factors.collect::<Vec<_>>().into_iter())
+}
+
+/// The polynomial in the secret whose evaluation the folded key for G2 vectors encodes.
+///
+/// The key starts at `X^n` and round `j` contributes the factor `1 + x_j X^(2^(k - 1 - j))`.
+fn w_key_polynomial<F: Field>(challenges: &[F], n: usize) -> Vec<F> {
+    let mut coefficients = vec![F::zero(); n];
+    coefficients.extend(product_coefficients(challenges.iter().rev().copied()));
+    coefficients
+}
+
+fn evaluate<F: Field>(coefficients: &[F], z: F) -> F {
+    coefficients
+        .iter()
+        .rev()
+        .fold(F::zero(), |acc, c| acc * z + c)
+}
+
+/// Returns the quotient of `f(X) - f(z)` by `X - z`.
+fn divide_by_linear<F: Field>(coefficients: &[F], z: F) -> Vec<F> {
+    let mut quotient = vec![F::zero(); coefficients.len().saturating_sub(1)];
+    let mut acc = F::zero();
+    for i in (1..coefficients.len()).rev() {
+        acc = coefficients[i] + z * acc;
+        quotient[i - 1] = acc;
+    }
+    quotient
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tests for proof aggregation, using sum circuit proofs.
+    use super::*;
+    use crate::circuits::groth16::{
+        from_bytes, generate_proof_with_key, prepare_verifying_key, setup, TrySerializer,
+    };
+    use crate::circuits::sum::SumCircuit;
+    use ark_bn254::{Bn254, Fr};
+
+    /// Creates sum proofs for `a + b` with the given pairs, under one key.
+    fn sum_proofs(
+        pairs: &[(u64, u64)],
+    ) -> (VerifyingKey<Bn254>, Vec<Vec<Fr>>, Vec<Groth16Proof<Bn254>>) {
+        let (pk, vk) = setup::<Bn254, _>(SumCircuit::default()).expect("keys created");
+        let public_inputs = pairs.iter().map(|(a, b)| vec![Fr::from(a + b)]).collect();
+        let proofs = pairs
+            .iter()
+            .map(|&(a, b)| {
+                generate_proof_with_key(
+                    &pk,
+                    SumCircuit::new(Some(a.into()), Some(b.into()), Some((a + b).into())),
+                )
+                .expect("proof created")
+            })
+            .collect();
+        (vk, public_inputs, proofs)
+    }
+
+    /// Test that an aggregate of five proofs, padded to eight, verifies and survives a
+    /// round trip through its serialization.
+    #[test]
+    fn aggregate_verify_sums() {
+        let key = setup_aggregation::<Bn254>(8).expect("aggregation key created");
+        let (vk, public_inputs, proofs) = sum_proofs(&[(1, 2), (3, 4), (5, 6), (7, 8), (9, 10)]);
+        let batch = public_inputs
+            .iter()
+            .zip(&proofs)
+            .map(|(x, p)| (x.as_slice(), p))
+            .collect::<Vec<_>>();
+        let aggregate = aggregate_proofs(&key, &vk, &batch).expect("proofs aggregated");
+        assert_eq!(aggregate.rounds.len(), 3);
+
+        let bytes = aggregate.try_to_bytes().expect("aggregate serialized");
+        let aggregate: AggregateProof<Bn254> = from_bytes(bytes).expect("aggregate deserialized");
+
+        let pvk = prepare_verifying_key(&vk);
+        let inputs = public_inputs.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        assert!(matches!(
+            verify_aggregate_proof(&key.vk, &pvk, &inputs, &aggregate),
+            Ok(true)
+        ));
+
+        let mut wrong_inputs = public_inputs.clone();
+        wrong_inputs[2] = vec![Fr::from(12u64)];
+        let wrong_inputs = wrong_inputs.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        assert!(matches!(
+            verify_aggregate_proof(&key.vk, &pvk, &wrong_inputs, &aggregate),
+            Ok(false)
+        ));
+        assert!(matches!(
+            verify_aggregate_proof(&key.vk, &pvk, &inputs[..4], &aggregate),
+            Ok(false)
+        ));
+    }
+
+    /// Test that a forged round count and a key without public input bases are rejected
+    /// instead of overflowing.
+    #[test]
+    fn aggregate_rejects_malformed_inputs() {
+        let key = setup_aggregation::<Bn254>(2).expect("aggregation key created");
+        let (vk, public_inputs, proofs) = sum_proofs(&[(1, 2), (3, 4)]);
+        let mut aggregate = aggregate_proofs(
+            &key,
+            &vk,
+            &[
+                (&public_inputs[0], &proofs[0]),
+                (&public_inputs[1], &proofs[1]),
+            ],
+        )
+        .expect("proofs aggregated");
+        let inputs = [public_inputs[0].as_slice(), public_inputs[1].as_slice()];
+
+        let round = aggregate.rounds[0].clone();
+        aggregate.rounds.resize(64, round);
+        let pvk = prepare_verifying_key(&vk);
+        assert!(matches!(
+            verify_aggregate_proof(&key.vk, &pvk, &inputs, &aggregate),
+            Ok(false)
+        ));
+
+        let mut malformed = vk.clone();
+        malformed.gamma_abc_g1.clear();
+        assert!(matches!(
+            verify_aggregate_proof(
+                &key.vk,
+                &prepare_verifying_key(&malformed),
+                &inputs,
+                &aggregate
+            ),
+            Err(Groth16Error::Synthesis(
+                SynthesisError::MalformedVerifyingKey
+            ))
+        ));
+    }
+
+    /// Test that a single proof can be aggregated on its own.
+    #[test]
+    fn aggregate_verify_single_sum() {
+        let key = setup_aggregation::<Bn254>(1).expect("aggregation key created");
+        let (vk, public_inputs, proofs) = sum_proofs(&[(10, 32)]);
+        let aggregate = aggregate_proofs(&key, &vk, &[(&public_inputs[0], &proofs[0])])
+            .expect("proofs aggregated");
+
+        let pvk = prepare_verifying_key(&vk);
+        assert!(matches!(
+            verify_aggregate_proof(&key.vk, &pvk, &[&public_inputs[0]], &aggregate),
+            Ok(true)
+        ));
+    }
+
+    /// Test that an invalid proof makes the aggregate fail, and that the key size is
+    /// enforced and checked against the lengths of its vectors.
+    #[test]
+    fn aggregate_rejects_invalid_proofs() {
+        let key = setup_aggregation::<Bn254>(2).expect("aggregation key created");
+        let (vk, public_inputs, mut proofs) = sum_proofs(&[(1, 2), (3, 4), (5, 6)]);
+
+        let batch = public_inputs
+            .iter()
+            .zip(&proofs)
+            .map(|(x, p)| (x.as_slice(), p))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            aggregate_proofs(&key, &vk, &batch),
+            Err(Groth16Error::TooManyProofs { max: 2, actual: 3 })
+        ));
+        assert!(matches!(
+            setup_aggregation::<Bn254>(0),
+            Err(Groth16Error::InvalidAggregationSize)
+        ));
+
+        // A deserialized key may have vectors too short for its size.
+        for truncate in [
+            |key: &mut AggregationKey<Bn254>| key.g_alpha_powers.truncate(2),
+            |key: &mut AggregationKey<Bn254>| key.g_beta_powers.truncate(3),
+            |key: &mut AggregationKey<Bn254>| key.h_beta_powers.truncate(1),
+            |key: &mut AggregationKey<Bn254>| key.h_alpha_powers.clear(),
+        ] {
+            let mut malformed = key.clone();
+            truncate(&mut malformed);
+            assert!(matches!(
+                aggregate_proofs(&malformed, &vk, &batch[..2]),
+                Err(Groth16Error::InvalidAggregationKey)
+            ));
+        }
+
+        proofs.swap(0, 1);
+        let aggregate = aggregate_proofs(
+            &key,
+            &vk,
+            &[
+                (&public_inputs[0], &proofs[0]),
+                (&public_inputs[1], &proofs[1]),
+            ],
+        )
+        .expect("proofs aggregated");
+        let pvk = prepare_verifying_key(&vk);
+        assert!(matches!(
+            verify_aggregate_proof(
+                &key.vk,
+                &pvk,
+                &[&public_inputs[0], &public_inputs[1]],
+                &aggregate
+            ),
+            Ok(false)
+        ));
+    }
+}
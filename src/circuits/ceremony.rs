@@ -0,0 +1,871 @@
+//! # Trusted Setup Ceremony
+//!
+//! Groth16 keys embed secrets whose knowledge allows forging proofs. This module computes
+//! the keys in a multi-party ceremony, so that they are safe as long as one participant
+//! discards their secrets. It follows the two phases of
+//! [Bowe, Gabizon and Miers](https://eprint.iacr.org/2017/1050):
+//!
+//! 1. Participants multiply the secrets `tau`, `alpha` and `beta` of the powers of tau
+//!    by their own secrets. Phase 1 is then closed by deriving the circuit parameters
+//!    from the powers.
+//! 2. Participants multiply the secret `delta` of the circuit parameters by their own
+//!    secrets.
+//!
+//! Every contribution publishes the images of its secrets with a Schnorr proof of
+//! knowledge bound to the transcript so far, which lets [`Ceremony::verify`] check that
+//! each contribution built on the previous one.
+//!
+//! ## Transcript Format
+//!
+//! A [`Ceremony`] is its own transcript: serializing it with `TrySerializer` writes the
+//! circuit digest, the current powers of tau, the phase 1 contributions, the circuit
+//! parameters once phase 1 is closed and the phase 2 contributions, in that order.
+//!
+
+use crate::circuits::groth16::{matrices_digest, synthesize_for_setup, Groth16Error};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{Field, PrimeField, UniformRand, Zero};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{thread_rng, CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Domain separator of the transcript hashes.
+const TRANSCRIPT_LABEL: &[u8] = b"trivial-circuits/ceremony/v1";
+
+/// The powers of the phase 1 secrets, for an evaluation domain of `n` elements.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PowersOfTau<E: Pairing> {
+    /// `tau^i` in G1 for `i < 2n - 1`.
+    pub tau_g1: Vec<E::G1Affine>,
+    /// `tau^i` in G2 for `i < n`.
+    pub tau_g2: Vec<E::G2Affine>,
+    /// `alpha * tau^i` in G1 for `i < n`.
+    pub alpha_tau_g1: Vec<E::G1Affine>,
+    /// `beta * tau^i` in G1 for `i < n`.
+    pub beta_tau_g1: Vec<E::G1Affine>,
+    /// `beta` in G2.
+    pub beta_g2: E::G2Affine,
+}
+
+impl<E: Pairing> PowersOfTau<E> {
+    /// Powers for a domain of `n` elements with every secret equal to one.
+    pub(crate) fn new(n: usize) -> Self {
+        let g1 = E::G1Affine::generator();
+        let g2 = E::G2Affine::generator();
+        Self {
+            tau_g1: vec![g1; 2 * n - 1],
+            tau_g2: vec![g2; n],
+            alpha_tau_g1: vec![g1; n],
+            beta_tau_g1: vec![g1; n],
+            beta_g2: g2,
+        }
+    }
+
+    /// The size of the largest evaluation domain these powers support.
+    pub fn domain_size(&self) -> usize {
+        self.tau_g2.len()
+    }
+
+    /// Checks that the vectors have the lengths of a domain of at least two elements,
+    /// which deserialized powers need not have.
+    fn has_consistent_lengths(&self) -> bool {
+        let n = self.domain_size();
+        n >= 2
+            && self.tau_g1.len() == 2 * n - 1
+            && self.alpha_tau_g1.len() == n
+            && self.beta_tau_g1.len() == n
+    }
+
+    /// The powers for the first `n` elements of the domain, which are powers of tau for a
+    /// domain of `n` elements.
+    ///
+    /// Fails with `InvalidPowersOfTau` if the vectors have inconsistent lengths or `n` is
+    /// not between 2 and the domain size.
+    pub(crate) fn truncate(&self, n: usize) -> Result<Self, Groth16Error> {
+        if !self.has_consistent_lengths() || n < 2 || n > self.domain_size() {
+            return Err(Groth16Error::InvalidPowersOfTau);
+        }
+        Ok(Self {
+            tau_g1: self.tau_g1[..2 * n - 1].to_vec(),
+            tau_g2: self.tau_g2[..n].to_vec(),
+            alpha_tau_g1: self.alpha_tau_g1[..n].to_vec(),
+            beta_tau_g1: self.beta_tau_g1[..n].to_vec(),
+            beta_g2: self.beta_g2,
+        })
+    }
+
+    /// Multiplies the secrets by `tau`, `alpha` and `beta`.
+    pub(crate) fn apply(
+        &mut self,
+        tau: E::ScalarField,
+        alpha: E::ScalarField,
+        beta: E::ScalarField,
+    ) {
+        let tau_powers = powers(tau, self.tau_g1.len());
+        self.tau_g1 = scale(&self.tau_g1, tau_powers.iter().copied());
+        self.tau_g2 = scale(&self.tau_g2, tau_powers.iter().copied());
+        self.alpha_tau_g1 = scale(&self.alpha_tau_g1, tau_powers.iter().map(|t| alpha * t));
+        self.beta_tau_g1 = scale(&self.beta_tau_g1, tau_powers.iter().map(|t| beta * t));
+        self.beta_g2 = (self.beta_g2 * beta).into_affine();
+    }
+
+    /// Checks that every vector holds successive powers of the same `tau`, and that
+    /// `beta_tau_g1` and `beta_g2` share the same `beta`.
+    pub(crate) fn is_well_formed(&self) -> bool {
+        if !self.has_consistent_lengths()
+            || self.tau_g1[0] != E::G1Affine::generator()
+            || self.tau_g2[0] != E::G2Affine::generator()
+        {
+            return false;
+        }
+        let g1 = E::G1Affine::generator();
+        let g2 = E::G2Affine::generator();
+        let tau_g2 = self.tau_g2[1];
+        let mut rng = thread_rng();
+        let mut successive = |points: &[E::G1Affine]| {
+            let (first, second) = random_shifts(points, &mut rng);
+            same_ratio::<E>(first, tau_g2, second, g2)
+        };
+        let g1_powers_hold = successive(&self.tau_g1)
+            && successive(&self.alpha_tau_g1)
+            && successive(&self.beta_tau_g1);
+        let (first, second) = random_shifts(&self.tau_g2, &mut rng);
+        g1_powers_hold
+            && same_ratio::<E>(self.tau_g1[1], g2, g1, tau_g2)
+            && same_ratio::<E>(self.tau_g1[1], first, g1, second)
+            && same_ratio::<E>(self.beta_tau_g1[0], g2, g1, self.beta_g2)
+    }
+
+    /// Derives the parameters of the circuit synthesized in `cs`, with `gamma` and `delta`
+    /// equal to one.
+    pub(crate) fn circuit_parameters(
+        &self,
+        cs: &ConstraintSystemRef<E::ScalarField>,
+    ) -> Result<ProvingKey<E>, Groth16Error> {
+        let num_instance_variables = cs.num_instance_variables();
+        let num_constraints = cs.num_constraints();
+        let domain = GeneralEvaluationDomain::<E::ScalarField>::new(
+            num_constraints + num_instance_variables,
+        )
+        .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let n = domain.size();
+        if !self.has_consistent_lengths() {
+            return Err(Groth16Error::InvalidPowersOfTau);
+        }
+        if n > self.domain_size() {
+            return Err(Groth16Error::CircuitTooLarge {
+                max: self.domain_size(),
+                actual: n,
+            });
+        }
+        let matrices = cs
+            .to_matrices()
+            .ok_or(Groth16Error::Synthesis(SynthesisError::MissingCS))?;
+
+        // The inverse FFT of the powers of tau gives the Lagrange polynomials of the
+        // domain evaluated at tau, which the QAP polynomials are combinations of.
+        let lagrange_g1 = domain.ifft(&to_group(&self.tau_g1[..n]));
+        let lagrange_g2 = domain.ifft(&to_group(&self.tau_g2[..n]));
+        let alpha_lagrange = domain.ifft(&to_group(&self.alpha_tau_g1[..n]));
+        let beta_lagrange = domain.ifft(&to_group(&self.beta_tau_g1[..n]));
+
+        // Mirrors the libsnark reduction used by ark-groth16, including the extra
+        // constraint per public input.
+        let num_variables = num_instance_variables + cs.num_witness_variables();
+        let mut a = vec![E::G1::zero(); num_variables];
+        let mut b_g1 = vec![E::G1::zero(); num_variables];
+        let mut b_g2 = vec![E::G2::zero(); num_variables];
+        let mut abc = vec![E::G1::zero(); num_variables];
+        for i in 0..num_instance_variables {
+            a[i] += lagrange_g1[num_constraints + i];
+            abc[i] += beta_lagrange[num_constraints + i];
+        }
+        let rows = matrices.a.iter().zip(&matrices.b).zip(&matrices.c);
+        for (j, ((row_a, row_b), row_c)) in rows.enumerate() {
+            for (coefficient, i) in row_a {
+                a[*i] += lagrange_g1[j] * coefficient;
+                abc[*i] += beta_lagrange[j] * coefficient;
+            }
+            for (coefficient, i) in row_b {
+                b_g1[*i] += lagrange_g1[j] * coefficient;
+                b_g2[*i] += lagrange_g2[j] * coefficient;
+                abc[*i] += alpha_lagrange[j] * coefficient;
+            }
+            for (coefficient, i) in row_c {
+                abc[*i] += lagrange_g1[j] * coefficient;
+            }
+        }
+
+        // tau^i * (tau^n - 1), the vanishing polynomial of the domain times tau^i.
+        let h_query = (0..n - 1)
+            .map(|i| self.tau_g1[i + n].into_group() - self.tau_g1[i])
+            .collect::<Vec<_>>();
+
+        let g1 = self.tau_g1[0];
+        let g2 = self.tau_g2[0];
+        Ok(ProvingKey {
+            vk: VerifyingKey {
+                alpha_g1: self.alpha_tau_g1[0],
+                beta_g2: self.beta_g2,
+                gamma_g2: g2,
+                delta_g2: g2,
+                gamma_abc_g1: E::G1::normalize_batch(&abc[..num_instance_variables]),
+            },
+            beta_g1: self.beta_tau_g1[0],
+            delta_g1: g1,
+            a_query: E::G1::normalize_batch(&a),
+            b_g1_query: E::G1::normalize_batch(&b_g1),
+            b_g2_query: E::G2::normalize_batch(&b_g2),
+            h_query: E::G1::normalize_batch(&h_query),
+            l_query: E::G1::normalize_batch(&abc[num_instance_variables..]),
+        })
+    }
+}
+
+/// The images of a contribution secret, with a proof that the contributor knows it.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeyShare<E: Pairing> {
+    /// The secret times the generator of G1.
+    pub g1: E::G1Affine,
+    /// The secret times the generator of G2.
+    pub g2: E::G2Affine,
+    /// The commitment of the Schnorr proof.
+    pub commitment: E::G1Affine,
+    /// The response of the Schnorr proof.
+    pub response: E::ScalarField,
+}
+
+impl<E: Pairing> KeyShare<E> {
+    fn new<R: RngCore + CryptoRng>(
+        secret: E::ScalarField,
+        label: &[u8],
+        hash: &[u8; 32],
+        rng: &mut R,
+    ) -> Result<Self, Groth16Error> {
+        let nonce = E::ScalarField::rand(rng);
+        let g1 = (E::G1Affine::generator() * secret).into_affine();
+        let g2 = (E::G2Affine::generator() * secret).into_affine();
+        let commitment = (E::G1Affine::generator() * nonce).into_affine();
+        let challenge = share_challenge::<E>(label, hash, &(g1, g2, commitment))?;
+        Ok(Self {
+            g1,
+            g2,
+            commitment,
+            response: nonce + challenge * secret,
+        })
+    }
+
+    /// Checks the proof of knowledge and that both images are of the same nonzero secret.
+    fn verify(&self, label: &[u8], hash: &[u8; 32]) -> Result<bool, Groth16Error> {
+        let challenge = share_challenge::<E>(label, hash, &(self.g1, self.g2, self.commitment))?;
+        let g1 = E::G1Affine::generator();
+        Ok(!self.g1.is_zero()
+            && g1 * self.response == self.g1 * challenge + self.commitment
+            && same_ratio::<E>(self.g1, E::G2Affine::generator(), g1, self.g2))
+    }
+}
+
+/// A contribution to phase 1, with the elements it produced.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Phase1Contribution<E: Pairing> {
+    pub tau: KeyShare<E>,
+    pub alpha: KeyShare<E>,
+    pub beta: KeyShare<E>,
+    /// `tau` in G1 after the contribution.
+    pub tau_g1: E::G1Affine,
+    /// `alpha` in G1 after the contribution.
+    pub alpha_g1: E::G1Affine,
+    /// `beta` in G1 after the contribution.
+    pub beta_g1: E::G1Affine,
+    /// `beta` in G2 after the contribution.
+    pub beta_g2: E::G2Affine,
+}
+
+/// A contribution to phase 2, with the element it produced.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Phase2Contribution<E: Pairing> {
+    pub delta: KeyShare<E>,
+    /// `delta` in G1 after the contribution.
+    pub delta_g1: E::G1Affine,
+}
+
+/// The state and transcript of a ceremony for one circuit.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Ceremony<E: Pairing> {
+    /// The `constraint_system_digest` of the circuit.
+    pub circuit_digest: [u8; 32],
+    pub powers: PowersOfTau<E>,
+    pub phase1: Vec<Phase1Contribution<E>>,
+    /// The circuit parameters, derived from `powers` when phase 1 is closed.
+    pub parameters: Option<ProvingKey<E>>,
+    pub phase2: Vec<Phase2Contribution<E>>,
+}
+
+impl<E: Pairing> Ceremony<E> {
+    /// Starts a ceremony for `circuit`, sized for its evaluation domain but at least two
+    /// so that `tau` itself is part of the powers.
+    pub fn new<C>(circuit: C) -> Result<Self, Groth16Error>
+    where
+        C: ConstraintSynthesizer<E::ScalarField>,
+    {
+        let cs = synthesize_for_setup(circuit)?;
+        Ok(Self {
+            circuit_digest: matrices_digest(&cs)?,
+            powers: PowersOfTau::new(domain_size::<E::ScalarField>(&cs)?.max(2)),
+            phase1: Vec::new(),
+            parameters: None,
+            phase2: Vec::new(),
+        })
+    }
+
+    /// Starts a ceremony for `circuit` from the output of a public powers-of-tau
+    /// ceremony, such as one read by `ptau::read_powers_of_tau`.
+    ///
+    /// Phase 1 is closed from the start, so every contribution is to phase 2.
+    pub fn from_powers_of_tau<C>(powers: &PowersOfTau<E>, circuit: C) -> Result<Self, Groth16Error>
+    where
+        C: ConstraintSynthesizer<E::ScalarField>,
+    {
+        let cs = synthesize_for_setup(circuit)?;
+        let n = domain_size::<E::ScalarField>(&cs)?.max(2);
+        if n > powers.domain_size() {
+            return Err(Groth16Error::CircuitTooLarge {
+                max: powers.domain_size(),
+                actual: n,
+            });
+        }
+        let powers = powers.truncate(n)?;
+        if !powers.is_well_formed() {
+            return Err(Groth16Error::InvalidPowersOfTau);
+        }
+        Ok(Self {
+            circuit_digest: matrices_digest(&cs)?,
+            parameters: Some(powers.circuit_parameters(&cs)?),
+            powers,
+            phase1: Vec::new(),
+            phase2: Vec::new(),
+        })
+    }
+
+    /// Adds a contribution to the open phase with secrets drawn from `rng`, returning the
+    /// transcript hash after it for the contributor to publish.
+    ///
+    /// The secrets are dropped on return; the ceremony is only as safe as that promise is
+    /// for at least one contributor of each phase.
+    pub fn contribute<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+    ) -> Result<[u8; 32], Groth16Error> {
+        let hash = self.transcript_hash()?;
+        match &mut self.parameters {
+            None => {
+                if !self.powers.has_consistent_lengths() {
+                    return Err(Groth16Error::InvalidPowersOfTau);
+                }
+                let tau = E::ScalarField::rand(rng);
+                let alpha = E::ScalarField::rand(rng);
+                let beta = E::ScalarField::rand(rng);
+                let contribution = Phase1Contribution {
+                    tau: KeyShare::new(tau, b"tau", &hash, rng)?,
+                    alpha: KeyShare::new(alpha, b"alpha", &hash, rng)?,
+                    beta: KeyShare::new(beta, b"beta", &hash, rng)?,
+                    tau_g1: (self.powers.tau_g1[1] * tau).into_affine(),
+                    alpha_g1: (self.powers.alpha_tau_g1[0] * alpha).into_affine(),
+                    beta_g1: (self.powers.beta_tau_g1[0] * beta).into_affine(),
+                    beta_g2: (self.powers.beta_g2 * beta).into_affine(),
+                };
+                self.powers.apply(tau, alpha, beta);
+                let next = chain_hash(&hash, &contribution)?;
+                self.phase1.push(contribution);
+                Ok(next)
+            }
+            Some(parameters) => {
+                let delta = E::ScalarField::rand(rng);
+                let delta_inverse = delta.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+                parameters.delta_g1 = (parameters.delta_g1 * delta).into_affine();
+                parameters.vk.delta_g2 = (parameters.vk.delta_g2 * delta).into_affine();
+                parameters.h_query = scale(&parameters.h_query, std::iter::repeat(delta_inverse));
+                parameters.l_query = scale(&parameters.l_query, std::iter::repeat(delta_inverse));
+                let contribution = Phase2Contribution {
+                    delta: KeyShare::new(delta, b"delta", &hash, rng)?,
+                    delta_g1: parameters.delta_g1,
+                };
+                let next = chain_hash(&hash, &contribution)?;
+                self.phase2.push(contribution);
+                Ok(next)
+            }
+        }
+    }
+
+    /// Ends phase 1 by deriving the parameters of `circuit` from the powers of tau.
+    pub fn close_phase1<C>(&mut self, circuit: C) -> Result<(), Groth16Error>
+    where
+        C: ConstraintSynthesizer<E::ScalarField>,
+    {
+        if self.parameters.is_some() || self.phase1.is_empty() {
+            return Err(Groth16Error::CeremonyPhase);
+        }
+        let cs = self.synthesize(circuit)?;
+        self.parameters = Some(self.powers.circuit_parameters(&cs)?);
+        Ok(())
+    }
+
+    /// Checks every contribution of both phases and that the parameters were derived from
+    /// the powers of tau for `circuit`.
+    pub fn verify<C>(&self, circuit: C) -> Result<(), Groth16Error>
+    where
+        C: ConstraintSynthesizer<E::ScalarField>,
+    {
+        let cs = self.synthesize(circuit)?;
+        if self.phase1.is_empty() {
+            return Err(Groth16Error::CeremonyPhase);
+        }
+        let hash = self.verify_phase1()?;
+        self.verify_phase2(&cs, hash)
+    }
+
+    /// Checks every contribution of a ceremony started with
+    /// [`Ceremony::from_powers_of_tau`], and that it was started from `powers`.
+    pub fn verify_with_powers_of_tau<C>(
+        &self,
+        powers: &PowersOfTau<E>,
+        circuit: C,
+    ) -> Result<(), Groth16Error>
+    where
+        C: ConstraintSynthesizer<E::ScalarField>,
+    {
+        let cs = self.synthesize(circuit)?;
+        if !self.phase1.is_empty() {
+            return Err(Groth16Error::CeremonyPhase);
+        }
+        let n = self.powers.domain_size();
+        if self.powers != powers.truncate(n)? {
+            return Err(Groth16Error::InvalidPowersOfTau);
+        }
+        self.verify_phase2(&cs, self.initial_hash()?)
+    }
+
+    /// Checks the phase 1 contributions and the powers they produced, returning the
+    /// transcript hash after them.
+    fn verify_phase1(&self) -> Result<[u8; 32], Groth16Error> {
+        let g1 = E::G1Affine::generator();
+        let g2 = E::G2Affine::generator();
+
+        let mut hash = self.initial_hash()?;
+        let (mut tau_g1, mut alpha_g1, mut beta_g1, mut beta_g2) = (g1, g1, g1, g2);
+        for (index, contribution) in self.phase1.iter().enumerate() {
+            let valid = contribution.tau.verify(b"tau", &hash)?
+                && contribution.alpha.verify(b"alpha", &hash)?
+                && contribution.beta.verify(b"beta", &hash)?
+                && same_ratio::<E>(contribution.tau_g1, g2, tau_g1, contribution.tau.g2)
+                && same_ratio::<E>(contribution.alpha_g1, g2, alpha_g1, contribution.alpha.g2)
+                && same_ratio::<E>(contribution.beta_g1, g2, beta_g1, contribution.beta.g2)
+                && same_ratio::<E>(g1, contribution.beta_g2, contribution.beta.g1, beta_g2);
+            if !valid {
+                return Err(Groth16Error::InvalidContribution { phase: 1, index });
+            }
+            (tau_g1, alpha_g1, beta_g1, beta_g2) = (
+                contribution.tau_g1,
+                contribution.alpha_g1,
+                contribution.beta_g1,
+                contribution.beta_g2,
+            );
+            hash = chain_hash(&hash, contribution)?;
+        }
+        // The last contributor produced the powers, so a malformed result is theirs.
+        let last = Groth16Error::InvalidContribution {
+            phase: 1,
+            index: self.phase1.len() - 1,
+        };
+        if !self.powers.is_well_formed()
+            || self.powers.tau_g1[1] != tau_g1
+            || self.powers.alpha_tau_g1[0] != alpha_g1
+            || self.powers.beta_tau_g1[0] != beta_g1
+            || self.powers.beta_g2 != beta_g2
+        {
+            return Err(last);
+        }
+        Ok(hash)
+    }
+
+    /// Checks the phase 2 contributions, starting from the transcript hash `hash`, and
+    /// that the parameters were derived from the powers of tau for `cs`.
+    fn verify_phase2(
+        &self,
+        cs: &ConstraintSystemRef<E::ScalarField>,
+        mut hash: [u8; 32],
+    ) -> Result<(), Groth16Error> {
+        let parameters = self
+            .parameters
+            .as_ref()
+            .ok_or(Groth16Error::CeremonyPhase)?;
+        if self.phase2.is_empty() {
+            return Err(Groth16Error::CeremonyPhase);
+        }
+        let g1 = E::G1Affine::generator();
+        let g2 = E::G2Affine::generator();
+
+        let mut delta_g1 = g1;
+        for (index, contribution) in self.phase2.iter().enumerate() {
+            let valid = contribution.delta.verify(b"delta", &hash)?
+                && same_ratio::<E>(contribution.delta_g1, g2, delta_g1, contribution.delta.g2);
+            if !valid {
+                return Err(Groth16Error::InvalidContribution { phase: 2, index });
+            }
+            delta_g1 = contribution.delta_g1;
+            hash = chain_hash(&hash, contribution)?;
+        }
+
+        // Everything but delta must be as derived from the powers, and the queries must
+        // have been divided by the final delta.
+        let last = Groth16Error::InvalidContribution {
+            phase: 2,
+            index: self.phase2.len() - 1,
+        };
+        let initial = self.powers.circuit_parameters(cs)?;
+        let delta_g2 = parameters.vk.delta_g2;
+        let unchanged = parameters.vk.alpha_g1 == initial.vk.alpha_g1
+            && parameters.vk.beta_g2 == initial.vk.beta_g2
+            && parameters.vk.gamma_g2 == initial.vk.gamma_g2
+            && parameters.vk.gamma_abc_g1 == initial.vk.gamma_abc_g1
+            && parameters.beta_g1 == initial.beta_g1
+            && parameters.a_query == initial.a_query
+            && parameters.b_g1_query == initial.b_g1_query
+            && parameters.b_g2_query == initial.b_g2_query;
+        if !unchanged
+            || parameters.delta_g1 != delta_g1
+            || !same_ratio::<E>(delta_g1, g2, g1, delta_g2)
+            || !divided_by::<E>(&parameters.h_query, &initial.h_query, delta_g2)
+            || !divided_by::<E>(&parameters.l_query, &initial.l_query, delta_g2)
+        {
+            return Err(last);
+        }
+        Ok(())
+    }
+
+    /// Returns the final keys, which `generate_proof` and `verify_proof` accept.
+    ///
+    /// Call [`Ceremony::verify`] on a transcript received from someone else first.
+    pub fn keys(&self) -> Result<(ProvingKey<E>, VerifyingKey<E>), Groth16Error> {
+        match &self.parameters {
+            Some(parameters) if !self.phase2.is_empty() => {
+                Ok((parameters.clone(), parameters.vk.clone()))
+            }
+            _ => Err(Groth16Error::CeremonyPhase),
+        }
+    }
+
+    /// The hash of the transcript, which the next contribution is bound to.
+    pub fn transcript_hash(&self) -> Result<[u8; 32], Groth16Error> {
+        let mut hash = self.initial_hash()?;
+        for contribution in &self.phase1 {
+            hash = chain_hash(&hash, contribution)?;
+        }
+        for contribution in &self.phase2 {
+            hash = chain_hash(&hash, contribution)?;
+        }
+        Ok(hash)
+    }
+
+    fn initial_hash(&self) -> Result<[u8; 32], Groth16Error> {
+        let size = self.powers.domain_size() as u64;
+        hash_with(TRANSCRIPT_LABEL, &(self.circuit_digest, size))
+    }
+
+    /// Synthesizes `circuit`, checking that it is the one the ceremony is for.
+    fn synthesize<C>(&self, circuit: C) -> Result<ConstraintSystemRef<E::ScalarField>, Groth16Error>
+    where
+        C: ConstraintSynthesizer<E::ScalarField>,
+    {
+        let cs = synthesize_for_setup(circuit)?;
+        if matrices_digest(&cs)? != self.circuit_digest {
+            return Err(Groth16Error::CircuitMismatch);
+        }
+        Ok(cs)
+    }
+}
+
+/// The number of powers of tau a ceremony for `circuit` needs, the size of its evaluation
+/// domain but at least two. Pass it to `ptau::read_powers_of_tau` to read only those.
+pub fn required_domain_size<F, C>(circuit: C) -> Result<usize, Groth16Error>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    Ok(domain_size(&synthesize_for_setup(circuit)?)?.max(2))
+}
+
+/// The size of the evaluation domain of the circuit synthesized in `cs`.
+fn domain_size<F: PrimeField>(cs: &ConstraintSystemRef<F>) -> Result<usize, Groth16Error> {
+    let domain =
+        GeneralEvaluationDomain::<F>::new(cs.num_constraints() + cs.num_instance_variables())
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+    Ok(domain.size())
+}
+
+fn hash_with<T: CanonicalSerialize>(prefix: &[u8], value: &T) -> Result<[u8; 32], Groth16Error> {
+    let mut hasher = Sha256::new();
+    hasher.update(prefix);
+    value
+        .serialize_compressed(&mut hasher)
+        .map_err(Groth16Error::Serialization)?;
+    Ok(hasher.finalize().into())
+}
+
+fn chain_hash<T: CanonicalSerialize>(hash: &[u8; 32], value: &T) -> Result<[u8; 32], Groth16Error> {
+    hash_with(hash, value)
+}
+
+fn share_challenge<E: Pairing>(
+    label: &[u8],
+    hash: &[u8; 32],
+    share: &(E::G1Affine, E::G2Affine, E::G1Affine),
+) -> Result<E::ScalarField, Groth16Error> {
+    let digest = hash_with(&[label, hash.as_slice()].concat(), share)?;
+    Ok(E::ScalarField::from_le_bytes_mod_order(&digest))
+}
+
+/// Checks that `a / c` in G1 equals `d / b` in G2, that is `e(a, b) == e(c, d)`.
+fn same_ratio<E: Pairing>(a: E::G1Affine, b: E::G2Affine, c: E::G1Affine, d: E::G2Affine) -> bool {
+    E::multi_pairing([a, (-c.into_group()).into_affine()], [b, d]).is_zero()
+}
+
+/// Checks that every element of `divided` is the element of `original` at the same index
+/// divided by the secret of `secret_g2`, with one pairing check on random combinations.
+fn divided_by<E: Pairing>(
+    divided: &[E::G1Affine],
+    original: &[E::G1Affine],
+    secret_g2: E::G2Affine,
+) -> bool {
+    if divided.len() != original.len() {
+        return false;
+    }
+    let mut rng = thread_rng();
+    let rho = (0..divided.len())
+        .map(|_| E::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let divided = E::G1::msm_unchecked(divided, &rho).into_affine();
+    let original = E::G1::msm_unchecked(original, &rho).into_affine();
+    same_ratio::<E>(divided, secret_g2, original, E::G2Affine::generator())
+}
+
+/// Returns random combinations of `points[..len - 1]` and `points[1..]` with the same
+/// coefficients, which are in the same ratio as consecutive points.
+fn random_shifts<G: AffineRepr, R: RngCore>(points: &[G], rng: &mut R) -> (G, G) {
+    let rho = (1..points.len())
+        .map(|_| G::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let first = G::Group::msm_unchecked(&points[..points.len() - 1], &rho);
+    let second = G::Group::msm_unchecked(&points[1..], &rho);
+    (first.into_affine(), second.into_affine())
+}
+
+/// Returns `[1, x, x^2, ..., x^(n-1)]`.
+fn powers<F: Field>(x: F, n: usize) -> Vec<F> {
+    std::iter::successors(Some(F::one()), |p| Some(*p * x))
+        .take(n)
+        .collect()
+}
+
+fn scale<G: AffineRepr>(points: &[G], scalars: impl Iterator<Item = G::ScalarField>) -> Vec<G> {
+    let scaled = points
+        .iter()
+        .zip(scalars)
+        .map(|(p, s)| *p * s)
+        .collect::<Vec<_>>();
+    G::Group::normalize_batch(&scaled)
+}
+
+fn to_group<G: AffineRepr>(points: &[G]) -> Vec<G::Group> {
+    points.iter().map(|p| p.into_group()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tests for the ceremony, run for the sum circuit.
+    use super::*;
+    use crate::circuits::compare::CompareCircuit;
+    use crate::circuits::groth16::{from_bytes, generate_proof, verify_proof, TrySerializer};
+    use crate::circuits::sum::SumCircuit;
+    use ark_bn254::{Bn254, Fr};
+    use ark_ec::Group;
+    use ark_ff::One;
+    use ark_groth16::Groth16;
+    use rand_chacha::rand_core::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// Runs a ceremony for the sum circuit with two contributions to each phase.
+    fn sum_ceremony() -> Ceremony<Bn254> {
+        let mut rng = thread_rng();
+        let mut ceremony = Ceremony::new(SumCircuit::default()).expect("ceremony started");
+        let first = ceremony.contribute(&mut rng).expect("contributed");
+        let second = ceremony.contribute(&mut rng).expect("contributed");
+        assert_ne!(first, second);
+        ceremony
+            .close_phase1(SumCircuit::default())
+            .expect("phase 1 closed");
+        ceremony.contribute(&mut rng).expect("contributed");
+        ceremony.contribute(&mut rng).expect("contributed");
+        ceremony
+    }
+
+    /// Test that the keys of a verified ceremony prove and verify, also after a round
+    /// trip of the transcript.
+    #[test]
+    fn ceremony_keys_prove_verify_sum() {
+        let ceremony = sum_ceremony();
+        let bytes = ceremony.try_to_bytes().expect("transcript serialized");
+        let ceremony: Ceremony<Bn254> = from_bytes(bytes).expect("transcript deserialized");
+        ceremony
+            .verify(SumCircuit::default())
+            .expect("ceremony is verified");
+
+        let (pk, vk) = ceremony.keys().expect("keys available");
+        let proof = generate_proof(
+            pk,
+            SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into())),
+        )
+        .expect("proof created");
+        let verified = verify_proof(vk, &[42.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    /// Test that the parameters derived from the powers of tau are the ones arkworks
+    /// generates from the same secrets.
+    #[test]
+    fn circuit_parameters_match_arkworks() {
+        let cs = synthesize_for_setup(SumCircuit::<Fr>::default()).expect("synthesized");
+        let domain =
+            GeneralEvaluationDomain::<Fr>::new(cs.num_constraints() + cs.num_instance_variables())
+                .expect("domain exists");
+
+        // arkworks draws tau from the rng first, so a copy of it yields the same value.
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let tau = domain.sample_element_outside_domain(&mut rng.clone());
+        let (alpha, beta) = (Fr::from(3u64), Fr::from(5u64));
+        let mut powers = PowersOfTau::<Bn254>::new(domain.size());
+        powers.apply(tau, alpha, beta);
+
+        let expected = Groth16::<Bn254>::generate_parameters_with_qap(
+            SumCircuit::default(),
+            alpha,
+            beta,
+            Fr::one(),
+            Fr::one(),
+            <Bn254 as Pairing>::G1::generator(),
+            <Bn254 as Pairing>::G2::generator(),
+            &mut rng,
+        )
+        .expect("parameters generated");
+        assert!(powers.is_well_formed());
+        assert_eq!(powers.circuit_parameters(&cs).expect("derived"), expected);
+    }
+
+    /// Test that powers whose G1 and G2 betas differ are rejected, as imported powers
+    /// have no contributions to check them against.
+    #[test]
+    fn powers_of_tau_rejects_inconsistent_beta() {
+        let mut powers = PowersOfTau::<Bn254>::new(8);
+        powers.apply(Fr::from(2u64), Fr::from(3u64), Fr::from(5u64));
+        assert!(powers.is_well_formed());
+
+        powers.beta_g2 = (powers.beta_g2 * Fr::from(2u64)).into_affine();
+        assert!(!powers.is_well_formed());
+        assert!(matches!(
+            Ceremony::from_powers_of_tau(&powers, SumCircuit::default()),
+            Err(Groth16Error::InvalidPowersOfTau)
+        ));
+    }
+
+    /// Test that powers with inconsistent vector lengths, as deserialized powers may have,
+    /// are rejected instead of panicking.
+    #[test]
+    fn powers_of_tau_rejects_inconsistent_lengths() {
+        let mut powers = PowersOfTau::<Bn254>::new(8);
+        powers.apply(Fr::from(2u64), Fr::from(3u64), Fr::from(5u64));
+        let mut ceremony =
+            Ceremony::from_powers_of_tau(&powers, SumCircuit::default()).expect("ceremony started");
+        ceremony.contribute(&mut thread_rng()).expect("contributed");
+
+        for malform in [
+            |powers: &mut PowersOfTau<Bn254>| powers.tau_g1.truncate(5),
+            |powers: &mut PowersOfTau<Bn254>| powers.tau_g1.push(powers.tau_g1[0]),
+            |powers: &mut PowersOfTau<Bn254>| powers.alpha_tau_g1.truncate(2),
+            |powers: &mut PowersOfTau<Bn254>| powers.beta_tau_g1.clear(),
+        ] {
+            let mut malformed = powers.clone();
+            malform(&mut malformed);
+            assert!(!malformed.is_well_formed());
+            assert!(matches!(
+                Ceremony::from_powers_of_tau(&malformed, SumCircuit::default()),
+                Err(Groth16Error::InvalidPowersOfTau)
+            ));
+            assert!(matches!(
+                ceremony.verify_with_powers_of_tau(&malformed, SumCircuit::default()),
+                Err(Groth16Error::InvalidPowersOfTau)
+            ));
+
+            let mut started = Ceremony::<Bn254>::new(SumCircuit::default()).expect("started");
+            let mut contributed = started.clone();
+            malform(&mut started.powers);
+            assert!(matches!(
+                started.contribute(&mut thread_rng()),
+                Err(Groth16Error::InvalidPowersOfTau)
+            ));
+            contributed
+                .contribute(&mut thread_rng())
+                .expect("contributed");
+            malform(&mut contributed.powers);
+            assert!(matches!(
+                contributed.close_phase1(SumCircuit::default()),
+                Err(Groth16Error::InvalidPowersOfTau)
+            ));
+        }
+    }
+
+    /// Test that tampered transcripts and other circuits are rejected.
+    #[test]
+    fn ceremony_rejects_tampering() {
+        let ceremony = sum_ceremony();
+        assert!(matches!(
+            ceremony.verify(CompareCircuit::<Fr>::with_prefix_length(3)),
+            Err(Groth16Error::CircuitMismatch)
+        ));
+
+        let mut forged = ceremony.clone();
+        forged.phase1[1].tau = forged.phase1[0].tau.clone();
+        assert!(matches!(
+            forged.verify(SumCircuit::default()),
+            Err(Groth16Error::InvalidContribution { phase: 1, index: 1 })
+        ));
+
+        let mut forged = ceremony.clone();
+        forged.phase2.swap(0, 1);
+        assert!(matches!(
+            forged.verify(SumCircuit::default()),
+            Err(Groth16Error::InvalidContribution { phase: 2, index: 0 })
+        ));
+
+        let mut forged = ceremony.clone();
+        let parameters = forged.parameters.as_mut().expect("phase 1 closed");
+        parameters.h_query[0] = parameters.h_query[1];
+        assert!(matches!(
+            forged.verify(SumCircuit::default()),
+            Err(Groth16Error::InvalidContribution { phase: 2, index: 1 })
+        ));
+
+        let mut unfinished = Ceremony::<Bn254>::new(SumCircuit::default()).expect("started");
+        assert!(matches!(
+            unfinished.close_phase1(SumCircuit::default()),
+            Err(Groth16Error::CeremonyPhase)
+        ));
+        assert!(matches!(
+            unfinished.keys(),
+            Err(Groth16Error::CeremonyPhase)
+        ));
+    }
+}
@@ -7,6 +7,8 @@
 //! the longer string starts with the shorter string without revealing the entire longer string.
 //!
 
+use crate::circuits::container::CircuitId;
+use crate::circuits::groth16::Groth16Error;
 use ark_ff::PrimeField;
 use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
@@ -17,7 +19,7 @@ use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisE
 /// This struct wraps a vector of field elements, where each element
 /// represents a character in the original string.
 #[derive(Clone, Default)]
-struct PrimeString<F: PrimeField>(Vec<F>);
+pub struct PrimeString<F: PrimeField>(Vec<F>);
 impl<F: PrimeField> From<&'static str> for PrimeString<F> {
     /// Converts a string to a vector of field elements.
     ///
@@ -28,13 +30,20 @@ impl<F: PrimeField> From<&'static str> for PrimeString<F> {
     ///
     /// * `value` - A static string to convert
     fn from(value: &'static str) -> Self {
-        Self(
-            value
-                .as_bytes()
-                .iter()
-                .map(|c| (*c as u64).into())
-                .collect(),
-        )
+        value.as_bytes().into()
+    }
+}
+
+impl<F: PrimeField> From<&[u8]> for PrimeString<F> {
+    /// Converts a byte string to a vector of field elements.
+    ///
+    /// Each byte is converted to a field element holding its value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The bytes to convert
+    fn from(value: &[u8]) -> Self {
+        Self(value.iter().map(|c| (*c as u64).into()).collect())
     }
 }
 
@@ -61,6 +70,27 @@ pub struct CompareCircuit<F: PrimeField> {
     pub larger: Option<Vec<F>>,
 }
 
+impl<F: PrimeField> CompareCircuit<F> {
+    /// Creates a circuit for prefixes of exactly `prefix_length` characters.
+    ///
+    /// The values are placeholders, so the circuit is only useful for generating keys
+    /// with `setup`.
+    pub fn with_prefix_length(prefix_length: usize) -> Self {
+        Self {
+            shorter: Some(vec![F::zero(); prefix_length]),
+            larger: Some(vec![F::zero(); prefix_length]),
+        }
+    }
+
+    /// Identifies the compare circuit for prefixes of `prefix_length` characters in key
+    /// and proof containers.
+    pub fn circuit_id(prefix_length: usize) -> Result<CircuitId, Groth16Error> {
+        CircuitId::cached("compare", prefix_length, || {
+            Self::with_prefix_length(prefix_length)
+        })
+    }
+}
+
 impl<F: PrimeField> ConstraintSynthesizer<F> for CompareCircuit<F> {
     /// Generates constraints for the compare circuit.
     ///
@@ -110,10 +140,16 @@ mod tests {
     //! Tests for the Compare Circuit.
     //!
     //! These tests demonstrate how to create, prove, and verify a compare circuit.
-    use crate::circuits::groth16::{generate_proof, setup, verify_proof};
+    use crate::circuits::groth16::{
+        generate_proof, generate_proof_with_rng, setup, setup_with_rng, verify_proof, TrySerializer,
+    };
+    use crate::circuits::test_vectors::assert_vector;
 
     use super::{CompareCircuit, PrimeString};
-    use ark_bn254::Fr;
+    use ark_bn254::{Bn254, Fr};
+    use rand_chacha::rand_core::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use sha2::{Digest, Sha256};
 
     /// Test that we can prove and verify that "abcdef" starts with "abc".
     ///
@@ -134,10 +170,39 @@ mod tests {
             shorter: Some(shorter_array.clone().into()),
         };
 
-        let (pk, vk) = setup(circuit.clone()).expect("keys created");
+        let (pk, vk) = setup::<Bn254, _>(circuit.clone()).expect("keys created");
         let proof = generate_proof(pk, circuit).expect("proof generated");
         let verified = verify_proof(vk, &Vec::<Fr>::from(shorter_array), proof).expect("verified");
 
         assert!(verified, "this can't be verified");
     }
+
+    /// Test that a fixed seed reproduces the keys and proof in `tests/vectors`.
+    #[test]
+    fn deterministic_compare_vectors() {
+        let shorter_array: PrimeString<Fr> = "abc".into();
+        let circuit = CompareCircuit {
+            larger: Some(PrimeString::<Fr>::from("abcdef").into()),
+            shorter: Some(shorter_array.clone().into()),
+        };
+
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (pk, vk) =
+            setup_with_rng::<Bn254, _, _>(circuit.clone(), &mut rng).expect("keys created");
+        let proof = generate_proof_with_rng(&pk, circuit, &mut rng).expect("proof generated");
+
+        let pk_bytes = pk.try_to_bytes().expect("proving key serialized");
+        assert_vector("compare_proving_key.sha256", &Sha256::digest(pk_bytes));
+        assert_vector(
+            "compare_verifying_key",
+            &vk.try_to_bytes().expect("key serialized"),
+        );
+        assert_vector(
+            "compare_proof",
+            &proof.0.try_to_bytes().expect("proof serialized"),
+        );
+
+        let verified = verify_proof(vk, &Vec::<Fr>::from(shorter_array), proof).expect("verified");
+        assert!(verified, "this can't be verified");
+    }
 }
@@ -0,0 +1,469 @@
+//! # Container Format
+//!
+//! This module wraps serialized keys and proofs in a header that says what they are, so
+//! that loading one for the wrong curve or circuit fails with a clear error instead of
+//! producing a proof that never verifies.
+//!
+//! A container is laid out as
+//!
+//! | Field          | Size     | Contents                                                 |
+//! |----------------|----------|----------------------------------------------------------|
+//! | magic          | 4        | `TCZK`                                                   |
+//! | version        | 2        | `FORMAT_VERSION`, little-endian                          |
+//! | kind           | 1        | 0 proving key, 1 verifying key, 2 proof                  |
+//! | curve          | 1        | 0 BN254, 1 BLS12-381, 2 BLS12-377                        |
+//! | name length    | 1        | length of the circuit name in bytes                      |
+//! | name           | variable | circuit name in UTF-8                                    |
+//! | digest         | 32       | `groth16::constraint_system_digest` of the circuit       |
+//! | compression    | 1        | 0 uncompressed, 1 compressed                             |
+//! | payload        | variable | the arkworks encoding of the key or proof                |
+//!
+//! The payload is always validated when it is read, and nothing may follow it.
+//!
+
+use crate::circuits::groth16::{constraint_system_digest, Groth16Error, SerializationMode};
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::{Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// The bytes every container starts with.
+pub const MAGIC: [u8; 4] = *b"TCZK";
+/// The version of the layout written by `to_bytes`.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// The type of artifact held by a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArtifactKind {
+    ProvingKey = 0,
+    VerifyingKey = 1,
+    Proof = 2,
+}
+
+/// The curve an artifact was made over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Bn254 = 0,
+    Bls12_381 = 1,
+    Bls12_377 = 2,
+}
+
+/// The circuit an artifact was made for: a name, and a digest of its constraint system
+/// that tells apart circuits of different shapes with the same name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitId {
+    pub name: String,
+    pub digest: [u8; 32],
+}
+
+/// The header of a container.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub kind: ArtifactKind,
+    pub curve: Curve,
+    pub circuit: CircuitId,
+    pub compressed: bool,
+}
+
+/// A pairing whose artifacts can be stored in a container.
+pub trait ContainerCurve: Pairing {
+    const CURVE: Curve;
+}
+
+impl ContainerCurve for Bn254 {
+    const CURVE: Curve = Curve::Bn254;
+}
+
+#[cfg(feature = "bls12-381")]
+impl ContainerCurve for crate::circuits::groth16::Bls12_381 {
+    const CURVE: Curve = Curve::Bls12_381;
+}
+
+#[cfg(feature = "bls12-377")]
+impl ContainerCurve for crate::circuits::groth16::Bls12_377 {
+    const CURVE: Curve = Curve::Bls12_377;
+}
+
+/// A key or proof that can be stored in a container.
+pub trait Artifact: CanonicalSerialize + CanonicalDeserialize {
+    const KIND: ArtifactKind;
+    const CURVE: Curve;
+}
+
+impl<E: ContainerCurve> Artifact for ProvingKey<E> {
+    const KIND: ArtifactKind = ArtifactKind::ProvingKey;
+    const CURVE: Curve = E::CURVE;
+}
+
+impl<E: ContainerCurve> Artifact for VerifyingKey<E> {
+    const KIND: ArtifactKind = ArtifactKind::VerifyingKey;
+    const CURVE: Curve = E::CURVE;
+}
+
+impl<E: ContainerCurve> Artifact for Proof<E> {
+    const KIND: ArtifactKind = ArtifactKind::Proof;
+    const CURVE: Curve = E::CURVE;
+}
+
+impl ArtifactKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        [Self::ProvingKey, Self::VerifyingKey, Self::Proof]
+            .into_iter()
+            .find(|kind| *kind as u8 == byte)
+    }
+}
+
+impl Curve {
+    fn from_byte(byte: u8) -> Option<Self> {
+        [Self::Bn254, Self::Bls12_381, Self::Bls12_377]
+            .into_iter()
+            .find(|curve| *curve as u8 == byte)
+    }
+}
+
+impl CircuitId {
+    /// Identifies `circuit` by `name` and the digest of its constraint system.
+    ///
+    /// The name must be at most 255 bytes long to be written to a container.
+    pub fn new<F, C>(name: impl Into<String>, circuit: C) -> Result<Self, Groth16Error>
+    where
+        F: PrimeField,
+        C: ConstraintSynthesizer<F>,
+    {
+        Ok(Self {
+            name: name.into(),
+            digest: constraint_system_digest(circuit)?,
+        })
+    }
+
+    /// Like `new`, but remembers the id for each field, `name` and `shape`, so a fixed
+    /// circuit is synthesized and hashed once per process rather than on every call.
+    ///
+    /// `shape` must tell apart the circuits that `circuit` can build under one name, such
+    /// as the prefix length of the compare circuit.
+    pub(crate) fn cached<F, C>(
+        name: &'static str,
+        shape: usize,
+        circuit: impl FnOnce() -> C,
+    ) -> Result<Self, Groth16Error>
+    where
+        F: PrimeField,
+        C: ConstraintSynthesizer<F>,
+    {
+        type Cache = Mutex<HashMap<(TypeId, &'static str, usize), CircuitId>>;
+        static CACHE: OnceLock<Cache> = OnceLock::new();
+
+        let cache = CACHE.get_or_init(Default::default);
+        let key = (TypeId::of::<F>(), name, shape);
+        if let Some(id) = cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+        {
+            return Ok(id.clone());
+        }
+        let id = Self::new(name, circuit())?;
+        cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, id.clone());
+        Ok(id)
+    }
+
+    /// Fails with `ArtifactMismatch` unless `self` is the `expected` circuit.
+    pub fn check(&self, expected: &CircuitId) -> Result<(), Groth16Error> {
+        check("circuit", expected, self)
+    }
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ArtifactKind::ProvingKey => "a proving key",
+            ArtifactKind::VerifyingKey => "a verifying key",
+            ArtifactKind::Proof => "a proof",
+        })
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Curve::Bn254 => "BN254",
+            Curve::Bls12_381 => "BLS12-381",
+            Curve::Bls12_377 => "BLS12-377",
+        })
+    }
+}
+
+impl fmt::Display for CircuitId {
+    /// Writes the name and the start of the digest, such as `sum (1f2e3d4c)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (", self.name)?;
+        for byte in &self.digest[..4] {
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Serializes `artifact` for `circuit` into a container, with the encoding of `mode`.
+pub fn to_bytes<T: Artifact>(
+    artifact: &T,
+    circuit: &CircuitId,
+    mode: SerializationMode,
+) -> Result<Vec<u8>, Groth16Error> {
+    let name = circuit.name.as_bytes();
+    let name_length = u8::try_from(name.len())
+        .map_err(|_| Groth16Error::Serialization(SerializationError::NotEnoughSpace))?;
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(FORMAT_VERSION.to_le_bytes());
+    bytes.extend([T::KIND as u8, T::CURVE as u8, name_length]);
+    bytes.extend(name);
+    bytes.extend(circuit.digest);
+    bytes.push(mode.compressed as u8);
+    artifact
+        .serialize_with_mode(&mut bytes, mode.compress())
+        .map_err(Groth16Error::Serialization)?;
+    Ok(bytes)
+}
+
+/// Deserializes a container written by `to_bytes` for the `expected` circuit.
+///
+/// Fails with `InvalidContainer` if `bytes` is not a container, and with
+/// `ArtifactMismatch` if it holds another kind of artifact, or one for another curve or
+/// circuit.
+pub fn from_bytes<T: Artifact>(bytes: &[u8], expected: &CircuitId) -> Result<T, Groth16Error> {
+    let (header, payload) = split_for::<T>(bytes)?;
+    header.circuit.check(expected)?;
+    decode(&header, payload)
+}
+
+/// Deserializes a container of the right kind and curve for any circuit, returning the
+/// circuit along with the artifact.
+///
+/// This is for loading keys before the circuit they will be used with is known; the
+/// circuit should be checked with `CircuitId::check` before use.
+pub fn open<T: Artifact>(bytes: &[u8]) -> Result<(CircuitId, T), Groth16Error> {
+    let (header, payload) = split_for::<T>(bytes)?;
+    let artifact = decode(&header, payload)?;
+    Ok((header.circuit, artifact))
+}
+
+/// Reads the header of a container without deserializing the artifact.
+pub fn read_header(bytes: &[u8]) -> Result<Header, Groth16Error> {
+    split(bytes).map(|(header, _)| header)
+}
+
+/// Splits a container into its header and payload.
+fn split(bytes: &[u8]) -> Result<(Header, &[u8]), Groth16Error> {
+    let mut rest = bytes;
+    let mut take = |n: usize| {
+        if rest.len() < n {
+            return Err(Groth16Error::InvalidContainer);
+        }
+        let (head, tail) = rest.split_at(n);
+        rest = tail;
+        Ok(head)
+    };
+
+    if take(MAGIC.len())? != MAGIC {
+        return Err(Groth16Error::InvalidContainer);
+    }
+    let version = u16::from_le_bytes(take(2)?.try_into().expect("two bytes"));
+    check("format version", &FORMAT_VERSION, &version)?;
+    let kind = ArtifactKind::from_byte(take(1)?[0]).ok_or(Groth16Error::InvalidContainer)?;
+    let curve = Curve::from_byte(take(1)?[0]).ok_or(Groth16Error::InvalidContainer)?;
+    let name_length = take(1)?[0] as usize;
+    let name = String::from_utf8(take(name_length)?.to_vec())
+        .map_err(|_| Groth16Error::InvalidContainer)?;
+    let digest = take(32)?.try_into().expect("32 bytes");
+    let compressed = match take(1)?[0] {
+        0 => false,
+        1 => true,
+        _ => return Err(Groth16Error::InvalidContainer),
+    };
+
+    let header = Header {
+        kind,
+        curve,
+        circuit: CircuitId { name, digest },
+        compressed,
+    };
+    Ok((header, rest))
+}
+
+/// Splits a container, checking that it holds a `T`.
+fn split_for<T: Artifact>(bytes: &[u8]) -> Result<(Header, &[u8]), Groth16Error> {
+    let (header, payload) = split(bytes)?;
+    check("artifact", &T::KIND, &header.kind)?;
+    check("curve", &T::CURVE, &header.curve)?;
+    Ok((header, payload))
+}
+
+fn decode<T: Artifact>(header: &Header, mut payload: &[u8]) -> Result<T, Groth16Error> {
+    let mode = if header.compressed {
+        SerializationMode::COMPRESSED
+    } else {
+        SerializationMode::UNCOMPRESSED
+    };
+    let artifact = T::deserialize_with_mode(&mut payload, mode.compress(), mode.validate())
+        .map_err(Groth16Error::Deserialization)?;
+    if !payload.is_empty() {
+        return Err(Groth16Error::Deserialization(
+            SerializationError::InvalidData,
+        ));
+    }
+    Ok(artifact)
+}
+
+fn check<T: PartialEq + fmt::Display>(
+    field: &'static str,
+    expected: &T,
+    actual: &T,
+) -> Result<(), Groth16Error> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Groth16Error::ArtifactMismatch {
+            field,
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tests for the container format, with keys and proofs for the sum and compare
+    //! circuits.
+    use super::*;
+    use crate::circuits::compare::CompareCircuit;
+    use crate::circuits::groth16::{generate_proof, setup, verify_proof, TrySerializer};
+    use crate::circuits::sum::SumCircuit;
+    use ark_bn254::Fr;
+
+    /// Test that keys and proofs survive a round trip in both encodings.
+    #[test]
+    fn container_round_trip() {
+        let sum = SumCircuit::<Fr>::circuit_id().expect("circuit identified");
+        let (pk, vk) = setup::<Bn254, _>(SumCircuit::default()).expect("keys created");
+        let proof = generate_proof(
+            pk.clone(),
+            SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into())),
+        )
+        .expect("proof created");
+
+        for mode in [
+            SerializationMode::UNCOMPRESSED,
+            SerializationMode::COMPRESSED,
+        ] {
+            let bytes = to_bytes(&pk, &sum, mode).expect("key serialized");
+            let header = read_header(&bytes).expect("header read");
+            assert_eq!(header.kind, ArtifactKind::ProvingKey);
+            assert_eq!(header.curve, Curve::Bn254);
+            assert_eq!(header.circuit, sum);
+            assert_eq!(header.compressed, mode.compressed);
+            let read: ProvingKey<Bn254> = from_bytes(&bytes, &sum).expect("key read");
+            assert_eq!(
+                read.try_to_bytes().expect("serialized"),
+                pk.try_to_bytes().expect("serialized")
+            );
+
+            let vk_bytes = to_bytes(&vk, &sum, mode).expect("key serialized");
+            let proof_bytes = to_bytes(proof.as_ref(), &sum, mode).expect("proof serialized");
+            let read_vk = from_bytes(&vk_bytes, &sum).expect("key read");
+            let read_proof: Proof<Bn254> = from_bytes(&proof_bytes, &sum).expect("proof read");
+            let verified =
+                verify_proof(read_vk, &[Fr::from(42u64)], read_proof.into()).expect("verified");
+            assert!(verified, "this can't be verified");
+        }
+    }
+
+    /// Test that circuit ids are computed once per circuit shape and field, and that the
+    /// cached ids are the ones `CircuitId::new` computes.
+    #[test]
+    fn circuit_ids_are_cached() {
+        let sum = CircuitId::new("sum", SumCircuit::<Fr>::default()).expect("circuit identified");
+        assert_eq!(
+            SumCircuit::<Fr>::circuit_id().expect("circuit identified"),
+            sum
+        );
+        let cached = CircuitId::cached::<Fr, SumCircuit<Fr>>("sum", 0, || unreachable!());
+        assert_eq!(cached.expect("circuit identified"), sum);
+
+        // Each prefix length is a different circuit, and so is each field.
+        let two = CompareCircuit::<Fr>::circuit_id(2).expect("circuit identified");
+        let three = CompareCircuit::<Fr>::circuit_id(3).expect("circuit identified");
+        assert_ne!(two, three);
+        assert_eq!(
+            CompareCircuit::<Fr>::circuit_id(2).expect("circuit identified"),
+            two
+        );
+        let other = CompareCircuit::<ark_bn254::Fq>::circuit_id(2);
+        assert_ne!(other.expect("circuit identified"), two);
+    }
+
+    /// Test that a key for one circuit, or of the wrong kind, can't be loaded for another.
+    #[test]
+    fn container_rejects_mismatches() {
+        let sum = SumCircuit::<Fr>::circuit_id().expect("circuit identified");
+        let compare = CompareCircuit::<Fr>::circuit_id(3).expect("circuit identified");
+        let (pk, _) =
+            setup::<Bn254, _>(CompareCircuit::<Fr>::with_prefix_length(3)).expect("keys created");
+        let bytes = to_bytes(&pk, &compare, SerializationMode::COMPRESSED).expect("serialized");
+
+        let error = from_bytes::<ProvingKey<Bn254>>(&bytes, &sum).expect_err("wrong circuit");
+        assert!(matches!(
+            error,
+            Groth16Error::ArtifactMismatch {
+                field: "circuit",
+                ..
+            }
+        ));
+        assert!(error.to_string().starts_with("the circuit is compare ("));
+
+        // Same name, different shape.
+        let longer = CompareCircuit::<Fr>::circuit_id(4).expect("circuit identified");
+        assert_eq!(longer.name, compare.name);
+        assert!(from_bytes::<ProvingKey<Bn254>>(&bytes, &longer).is_err());
+
+        let error = from_bytes::<VerifyingKey<Bn254>>(&bytes, &compare).expect_err("wrong kind");
+        assert_eq!(
+            error.to_string(),
+            "the artifact is a proving key but a verifying key was expected"
+        );
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(
+            from_bytes::<ProvingKey<Bn254>>(&version, &compare)
+                .expect_err("wrong version")
+                .to_string(),
+            "the format version is 2 but 1 was expected"
+        );
+
+        // Raw arkworks bytes, truncated containers and trailing bytes are all rejected.
+        let raw = pk.try_to_bytes().expect("serialized");
+        assert!(matches!(
+            from_bytes::<ProvingKey<Bn254>>(&raw, &compare),
+            Err(Groth16Error::InvalidContainer)
+        ));
+        assert!(matches!(
+            from_bytes::<ProvingKey<Bn254>>(&bytes[..40], &compare),
+            Err(Groth16Error::InvalidContainer)
+        ));
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(matches!(
+            from_bytes::<ProvingKey<Bn254>>(&trailing, &compare),
+            Err(Groth16Error::Deserialization(_))
+        ));
+    }
+}
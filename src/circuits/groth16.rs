@@ -1,69 +1,868 @@
+//! Groth16 setup, proving and verification over any pairing-friendly curve.
+//!
+//! Every function is generic over `ark_ec::pairing::Pairing`. BN254 is always available,
+//! and BLS12-381 and BLS12-377 are enabled by the `bls12-381` and `bls12-377` features.
+
 use ark_bn254::Bn254;
-use ark_ec::pairing::Pairing;
-use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
-use ark_relations::r1cs::ConstraintSynthesizer;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{PrimeField, UniformRand, Zero};
+use ark_groth16::r1cs_to_qap::{LibsnarkReduction, R1CSToQAP};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey};
+use ark_poly::GeneralEvaluationDomain;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal, SynthesisError,
+    SynthesisMode,
+};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate,
+};
 use ark_snark::SNARK;
-use rand::thread_rng;
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+#[cfg(feature = "bls12-377")]
+pub use ark_bls12_377::Bls12_377;
+#[cfg(feature = "bls12-381")]
+pub use ark_bls12_381::Bls12_381;
+
+/// Errors returned by the functions in this module.
+#[derive(Debug)]
+pub enum Groth16Error {
+    /// A key or proof could not be serialized.
+    Serialization(SerializationError),
+    /// Bytes could not be deserialized into a key or proof.
+    Deserialization(SerializationError),
+    /// The circuit could not be synthesized, or the proof system rejected it.
+    Synthesis(SynthesisError),
+    /// The witness does not satisfy the constraint system.
+    Unsatisfied,
+    /// The number of public inputs does not match the verifying key.
+    PublicInputMismatch { expected: usize, actual: usize },
+    /// Proving was stopped by the progress callback.
+    Cancelled,
+    /// An aggregate was requested for an empty set of proofs.
+    NoProofs,
+    /// More proofs were given than the aggregation key supports.
+    TooManyProofs { max: usize, actual: usize },
+    /// An aggregation key was requested for no proofs.
+    InvalidAggregationSize,
+    /// The vectors of an aggregation key do not have the lengths its size needs.
+    InvalidAggregationKey,
+    /// A ceremony contribution, or the state it produced, failed verification.
+    InvalidContribution { phase: usize, index: usize },
+    /// The circuit is not the one the ceremony was started for.
+    CircuitMismatch,
+    /// The ceremony is not at the stage the requested step needs.
+    CeremonyPhase,
+    /// The circuit needs a larger evaluation domain than the powers of tau support.
+    CircuitTooLarge { max: usize, actual: usize },
+    /// The powers of tau are malformed or are not the expected ones.
+    InvalidPowersOfTau,
+    /// The bytes are not a container from `circuits::container`.
+    InvalidContainer,
+    /// A container holds another version, kind, curve or circuit than was expected.
+    ArtifactMismatch {
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for Groth16Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Groth16Error::Serialization(e) => write!(f, "serialization failed: {e}"),
+            Groth16Error::Deserialization(e) => write!(f, "deserialization failed: {e}"),
+            Groth16Error::Synthesis(e) => write!(f, "synthesis failed: {e}"),
+            Groth16Error::Unsatisfied => write!(f, "constraint system is not satisfied"),
+            Groth16Error::PublicInputMismatch { expected, actual } => write!(
+                f,
+                "expected {expected} public inputs but {actual} were given"
+            ),
+            Groth16Error::Cancelled => write!(f, "proving was cancelled"),
+            Groth16Error::NoProofs => write!(f, "at least one proof is required"),
+            Groth16Error::TooManyProofs { max, actual } => write!(
+                f,
+                "the aggregation key supports {max} proofs but {actual} were given"
+            ),
+            Groth16Error::InvalidAggregationSize => {
+                write!(f, "an aggregation key must support at least one proof")
+            }
+            Groth16Error::InvalidAggregationKey => write!(f, "the aggregation key is malformed"),
+            Groth16Error::InvalidContribution { phase, index } => {
+                write!(f, "contribution {index} to phase {phase} is invalid")
+            }
+            Groth16Error::CircuitMismatch => write!(f, "the circuit does not match the ceremony"),
+            Groth16Error::CeremonyPhase => {
+                write!(f, "the ceremony is not at the stage this step needs")
+            }
+            Groth16Error::CircuitTooLarge { max, actual } => write!(
+                f,
+                "the circuit needs a domain of {actual} but the powers of tau support {max}"
+            ),
+            Groth16Error::InvalidPowersOfTau => {
+                write!(f, "the powers of tau are malformed or do not match")
+            }
+            Groth16Error::InvalidContainer => {
+                write!(f, "the bytes are not a key or proof container")
+            }
+            Groth16Error::ArtifactMismatch {
+                field,
+                expected,
+                actual,
+            } => write!(f, "the {field} is {actual} but {expected} was expected"),
+        }
+    }
+}
+
+impl std::error::Error for Groth16Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Groth16Error::Serialization(e) | Groth16Error::Deserialization(e) => Some(e),
+            Groth16Error::Synthesis(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SynthesisError> for Groth16Error {
+    fn from(e: SynthesisError) -> Self {
+        Groth16Error::Synthesis(e)
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct Bn254Proof(pub Proof<Bn254>);
+pub struct Groth16Proof<E: Pairing>(pub Proof<E>);
+
+pub type Bn254Proof = Groth16Proof<Bn254>;
+
+#[cfg(feature = "bls12-381")]
+pub type Bls12_381Proof = Groth16Proof<Bls12_381>;
 
-impl AsRef<Proof<Bn254>> for Bn254Proof {
-    fn as_ref(&self) -> &Proof<Bn254> {
+#[cfg(feature = "bls12-377")]
+pub type Bls12_377Proof = Groth16Proof<Bls12_377>;
+
+impl<E: Pairing> AsRef<Proof<E>> for Groth16Proof<E> {
+    fn as_ref(&self) -> &Proof<E> {
         &self.0
     }
 }
 
-impl From<Proof<Bn254>> for Bn254Proof {
-    fn from(proof: Proof<Bn254>) -> Self {
-        Bn254Proof(proof)
+impl<E: Pairing> From<Proof<E>> for Groth16Proof<E> {
+    fn from(proof: Proof<E>) -> Self {
+        Groth16Proof(proof)
+    }
+}
+
+/// How keys and proofs are encoded and validated.
+///
+/// Compressed encodings store only the x-coordinate of each curve point, which halves the
+/// size of a proof at the cost of a square root per point when decoding. Checked decoding
+/// verifies that every point is on the curve and in the right subgroup; skip it only for
+/// bytes from a trusted source. The mode used to decode must match the encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerializationMode {
+    pub compressed: bool,
+    pub checked: bool,
+}
+
+impl SerializationMode {
+    /// Uncompressed and checked, the mode of `try_to_bytes` and `from_bytes`.
+    pub const UNCOMPRESSED: Self = Self {
+        compressed: false,
+        checked: true,
+    };
+
+    /// Compressed and checked.
+    pub const COMPRESSED: Self = Self {
+        compressed: true,
+        checked: true,
+    };
+
+    /// The same encoding, decoded without validation.
+    pub const fn unchecked(self) -> Self {
+        Self {
+            checked: false,
+            ..self
+        }
+    }
+
+    pub(crate) fn compress(self) -> Compress {
+        if self.compressed {
+            Compress::Yes
+        } else {
+            Compress::No
+        }
+    }
+
+    pub(crate) fn validate(self) -> Validate {
+        if self.checked {
+            Validate::Yes
+        } else {
+            Validate::No
+        }
+    }
+}
+
+impl Default for SerializationMode {
+    fn default() -> Self {
+        Self::UNCOMPRESSED
     }
 }
 
 pub trait TrySerializer {
-    fn try_to_bytes(&self) -> Result<Vec<u8>, String>;
+    fn try_to_bytes(&self) -> Result<Vec<u8>, Groth16Error> {
+        self.try_to_bytes_with_mode(SerializationMode::UNCOMPRESSED)
+    }
+
+    /// Serializes with the encoding chosen by `mode`; validation does not apply here.
+    fn try_to_bytes_with_mode(&self, mode: SerializationMode) -> Result<Vec<u8>, Groth16Error>;
 }
 
 impl<T> TrySerializer for T
 where
     T: CanonicalSerialize,
 {
-    fn try_to_bytes(&self) -> Result<Vec<u8>, String> {
+    fn try_to_bytes_with_mode(&self, mode: SerializationMode) -> Result<Vec<u8>, Groth16Error> {
         let mut bytes = Vec::<u8>::new();
-        self.serialize_uncompressed(&mut bytes)
-            .map_err(|e| e.to_string())?;
+        self.serialize_with_mode(&mut bytes, mode.compress())
+            .map_err(Groth16Error::Serialization)?;
         Ok(bytes)
     }
 }
 
-pub fn from_bytes<T>(bytes: Vec<u8>) -> Result<T, String>
+/// A value that is decoded from its bytes alone. Keys and proofs only hold for the
+/// circuit they were made for, so they are read with `container::from_bytes`, which
+/// checks it, rather than with `from_bytes`.
+pub trait Standalone: CanonicalDeserialize {}
+
+impl<E: Pairing> Standalone for crate::circuits::ceremony::Ceremony<E> {}
+
+impl<E: Pairing> Standalone for crate::circuits::aggregation::AggregateProof<E> {}
+
+pub fn from_bytes<T>(bytes: Vec<u8>) -> Result<T, Groth16Error>
+where
+    T: Standalone,
+{
+    from_bytes_with_mode(bytes, SerializationMode::UNCOMPRESSED)
+}
+
+/// Deserializes bytes produced by `try_to_bytes_with_mode` with the same encoding.
+pub fn from_bytes_with_mode<T>(bytes: Vec<u8>, mode: SerializationMode) -> Result<T, Groth16Error>
+where
+    T: Standalone,
+{
+    decode_with_mode(bytes, mode)
+}
+
+/// Deserializes any value like `from_bytes_with_mode`, without a container's checks.
+pub(crate) fn decode_with_mode<T>(
+    bytes: Vec<u8>,
+    mode: SerializationMode,
+) -> Result<T, Groth16Error>
 where
     T: CanonicalDeserialize,
 {
-    T::deserialize_uncompressed(bytes.as_slice()).map_err(|e| e.to_string())
+    T::deserialize_with_mode(bytes.as_slice(), mode.compress(), mode.validate())
+        .map_err(Groth16Error::Deserialization)
+}
+
+pub fn setup<E, C>(c: C) -> Result<(ProvingKey<E>, VerifyingKey<E>), Groth16Error>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    setup_with_rng(c, &mut thread_rng())
+}
+
+/// Generates keys like `setup`, drawing the secret parameters from `rng`.
+///
+/// **Not for production keys.** Anyone who can reproduce `rng` learns the secrets of
+/// the setup and can forge proofs for any public input. This exists so that tests can
+/// produce the same keys byte for byte from a fixed seed.
+pub fn setup_with_rng<E, C, R>(
+    c: C,
+    rng: &mut R,
+) -> Result<(ProvingKey<E>, VerifyingKey<E>), Groth16Error>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: RngCore + CryptoRng,
+{
+    Ok(Groth16::<E>::circuit_specific_setup(c, rng)?)
+}
+
+/// Synthesizes `c` without a witness, the way the Groth16 generator does.
+pub(crate) fn synthesize_for_setup<F, C>(c: C) -> Result<ConstraintSystemRef<F>, Groth16Error>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    c.generate_constraints(cs.clone())?;
+    cs.finalize();
+    Ok(cs)
+}
+
+/// Returns a SHA-256 digest of the constraint matrices of `c`, which identifies the
+/// circuit independently of any witness.
+pub fn constraint_system_digest<F, C>(c: C) -> Result<[u8; 32], Groth16Error>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    matrices_digest(&synthesize_for_setup(c)?)
+}
+
+pub(crate) fn matrices_digest<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+) -> Result<[u8; 32], Groth16Error> {
+    let matrices = cs
+        .to_matrices()
+        .ok_or(Groth16Error::Synthesis(SynthesisError::MissingCS))?;
+    let mut hasher = Sha256::new();
+    (
+        matrices.num_instance_variables,
+        matrices.num_witness_variables,
+        matrices.num_constraints,
+    )
+        .serialize_compressed(&mut hasher)
+        .map_err(Groth16Error::Serialization)?;
+    for matrix in [&matrices.a, &matrices.b, &matrices.c] {
+        matrix
+            .serialize_compressed(&mut hasher)
+            .map_err(Groth16Error::Serialization)?;
+    }
+    Ok(hasher.finalize().into())
+}
+
+pub fn generate_proof<E, C>(pk: ProvingKey<E>, c: C) -> Result<Groth16Proof<E>, Groth16Error>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    generate_proof_with_key(&pk, c)
+}
+
+/// Generates a proof without taking ownership of the proving key, so that one key
+/// can be reused for many proofs.
+pub fn generate_proof_with_key<E, C>(
+    pk: &ProvingKey<E>,
+    c: C,
+) -> Result<Groth16Proof<E>, Groth16Error>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    generate_proof_with_rng(pk, c, &mut thread_rng())
+}
+
+/// Generates a proof like `generate_proof_with_key`, drawing its blinding factors from
+/// `rng`.
+///
+/// **Not for production proofs.** Proofs stay sound, but a reproducible `rng` makes
+/// their blinding predictable, which can leak the private inputs. This exists so that
+/// tests can produce the same proof byte for byte from a fixed seed.
+pub fn generate_proof_with_rng<E, C, R>(
+    pk: &ProvingKey<E>,
+    c: C,
+    rng: &mut R,
+) -> Result<Groth16Proof<E>, Groth16Error>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: RngCore + CryptoRng,
+{
+    prove_in_phases(pk, c, rng, |_| true)
+}
+
+/// The phases of proof generation, reported by `generate_proof_with_progress` as each
+/// one starts.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvingPhase {
+    /// Generating the constraints and witness of the circuit.
+    Synthesis,
+    /// Computing the coefficients of the quotient polynomial.
+    WitnessMap,
+    /// The multi-scalar multiplication over the quotient polynomial query.
+    MsmH,
+    /// The multi-scalar multiplication over the witness query.
+    MsmL,
+    /// The multi-scalar multiplication that computes `A`.
+    MsmA,
+    /// The multi-scalar multiplication that computes `B` in G1.
+    MsmBG1,
+    /// The multi-scalar multiplication that computes `B` in G2.
+    MsmBG2,
+}
+
+/// Generates a proof like `generate_proof_with_key`, calling `progress` as each phase
+/// starts.
+///
+/// Proving stops with an error as soon as `progress` returns `false`. A phase that has
+/// already started runs to completion, so cancellation takes effect at the next phase.
+pub fn generate_proof_with_progress<E, C, P>(
+    pk: &ProvingKey<E>,
+    c: C,
+    progress: P,
+) -> Result<Groth16Proof<E>, Groth16Error>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    P: FnMut(ProvingPhase) -> bool,
+{
+    prove_in_phases(pk, c, &mut thread_rng(), progress)
 }
 
-pub fn setup<C>(c: C) -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>), String>
+/// Synthesizes `c` once and proves from its assignment, calling `progress` as each
+/// phase starts.
+///
+/// Groth16 asserts that the witness satisfies the constraints while proving, so this
+/// checks it first to report a wrong witness as an error rather than a panic.
+fn prove_in_phases<E, C, R, P>(
+    pk: &ProvingKey<E>,
+    c: C,
+    rng: &mut R,
+    mut progress: P,
+) -> Result<Groth16Proof<E>, Groth16Error>
 where
-    C: ConstraintSynthesizer<<Bn254 as Pairing>::ScalarField>,
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: RngCore + CryptoRng,
+    P: FnMut(ProvingPhase) -> bool,
 {
-    Groth16::<Bn254>::circuit_specific_setup(c, &mut thread_rng()).map_err(|e| e.to_string())
+    let mut enter = |phase| {
+        if progress(phase) {
+            Ok(())
+        } else {
+            Err(Groth16Error::Cancelled)
+        }
+    };
+
+    enter(ProvingPhase::Synthesis)?;
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    c.generate_constraints(cs.clone())?;
+    if !cs.is_satisfied()? {
+        return Err(Groth16Error::Unsatisfied);
+    }
+    cs.finalize();
+
+    enter(ProvingPhase::WitnessMap)?;
+    let h = LibsnarkReduction::witness_map::<
+        E::ScalarField,
+        GeneralEvaluationDomain<E::ScalarField>,
+    >(cs.clone())?;
+
+    // The rest mirrors the private `create_proof_with_assignment` of ark-groth16, split
+    // up so that each multi-scalar multiplication is reported.
+    let prover = cs
+        .borrow()
+        .ok_or(Groth16Error::Synthesis(SynthesisError::MissingCS))?;
+    let input_assignment = prover.instance_assignment[1..]
+        .iter()
+        .map(|x| x.into_bigint())
+        .collect::<Vec<_>>();
+    let aux_assignment = prover
+        .witness_assignment
+        .iter()
+        .map(|x| x.into_bigint())
+        .collect::<Vec<_>>();
+
+    let r = E::ScalarField::rand(rng);
+    let s = E::ScalarField::rand(rng);
+
+    enter(ProvingPhase::MsmH)?;
+    let h_assignment = h.iter().map(|x| x.into_bigint()).collect::<Vec<_>>();
+    let h_acc = E::G1::msm_bigint(&pk.h_query, &h_assignment);
+
+    enter(ProvingPhase::MsmL)?;
+    let l_aux_acc = E::G1::msm_bigint(&pk.l_query, &aux_assignment);
+
+    let assignment = [&input_assignment[..], &aux_assignment[..]].concat();
+
+    enter(ProvingPhase::MsmA)?;
+    let g_a = linear_combination(pk.delta_g1 * r, &pk.a_query, pk.vk.alpha_g1, &assignment);
+
+    enter(ProvingPhase::MsmBG1)?;
+    let g1_b = linear_combination(pk.delta_g1 * s, &pk.b_g1_query, pk.beta_g1, &assignment);
+
+    enter(ProvingPhase::MsmBG2)?;
+    let g2_b = linear_combination(
+        pk.vk.delta_g2 * s,
+        &pk.b_g2_query,
+        pk.vk.beta_g2,
+        &assignment,
+    );
+
+    let g_c = g_a * s + g1_b * r - pk.delta_g1 * (r * s) + l_aux_acc + h_acc;
+
+    Ok(Proof {
+        a: g_a.into_affine(),
+        b: g2_b.into_affine(),
+        c: g_c.into_affine(),
+    }
+    .into())
 }
 
-pub fn generate_proof<C>(pk: ProvingKey<Bn254>, c: C) -> Result<Bn254Proof, String>
+/// Computes `initial + query[0] + vk_param` plus the multi-scalar multiplication of the
+/// rest of `query` by `assignment`.
+fn linear_combination<G>(
+    initial: G::Group,
+    query: &[G],
+    vk_param: G,
+    assignment: &[<G::ScalarField as PrimeField>::BigInt],
+) -> G::Group
 where
-    C: ConstraintSynthesizer<<Bn254 as Pairing>::ScalarField>,
+    G: AffineRepr,
+    G::Group: VariableBaseMSM<MulBase = G>,
 {
-    Ok(Groth16::<Bn254>::prove(&pk, c, &mut thread_rng())
-        .map_err(|e| e.to_string())?
-        .into())
+    initial + query[0] + G::Group::msm_bigint(&query[1..], assignment) + vk_param
 }
 
-pub fn verify_proof(
-    vk: VerifyingKey<Bn254>,
-    public_input: &[<Bn254 as Pairing>::ScalarField],
-    proof: Bn254Proof,
-) -> Result<bool, String> {
-    Groth16::<Bn254>::verify(&vk, public_input, proof.as_ref()).map_err(|e| e.to_string())
+pub fn verify_proof<E: Pairing>(
+    vk: VerifyingKey<E>,
+    public_input: &[E::ScalarField],
+    proof: Groth16Proof<E>,
+) -> Result<bool, Groth16Error> {
+    check_public_input(&vk, public_input)?;
+    Ok(Groth16::<E>::verify(&vk, public_input, proof.as_ref())?)
+}
+
+pub fn prepare_verifying_key<E: Pairing>(vk: &VerifyingKey<E>) -> PreparedVerifyingKey<E> {
+    ark_groth16::prepare_verifying_key(vk)
+}
+
+/// Verifies a proof against a verifying key prepared with `prepare_verifying_key`,
+/// which skips the pairing that `verify_proof` computes on every call.
+pub fn verify_proof_with_prepared_key<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    public_input: &[E::ScalarField],
+    proof: &Groth16Proof<E>,
+) -> Result<bool, Groth16Error> {
+    check_public_input(&pvk.vk, public_input)?;
+    Ok(Groth16::<E>::verify_with_processed_vk(
+        pvk,
+        public_input,
+        proof.as_ref(),
+    )?)
+}
+
+/// Verifies many proofs against one prepared verifying key at once.
+///
+/// The proofs are combined with random coefficients so that they share a single
+/// multi-Miller loop and final exponentiation. If the combined check fails, every proof
+/// is verified on its own to find the invalid ones. Returns the indices of the proofs
+/// that failed, which is empty when all of them are valid.
+pub fn verify_proofs_batch<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    batch: &[(&[E::ScalarField], &Groth16Proof<E>)],
+) -> Result<Vec<usize>, Groth16Error> {
+    for (public_input, _) in batch {
+        check_public_input(&pvk.vk, public_input)?;
+    }
+    if batch.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // 128-bit coefficients make the chance of an invalid proof passing negligible while
+    // keeping the scalar multiplications short.
+    let mut rng = thread_rng();
+    let coefficients = batch
+        .iter()
+        .map(|_| E::ScalarField::from(rng.gen::<u128>()))
+        .collect::<Vec<_>>();
+
+    // Each proof must satisfy e(A, B) = e(alpha, beta) e(L, gamma) e(C, delta), where L
+    // is the linear combination of the public inputs. Raising each equation to its
+    // coefficient and multiplying them together lets the L and C terms be summed first.
+    let mut input_scalars = vec![E::ScalarField::zero(); pvk.vk.gamma_abc_g1.len()];
+    for ((public_input, _), r) in batch.iter().zip(&coefficients) {
+        input_scalars[0] += r;
+        for (acc, x) in input_scalars[1..].iter_mut().zip(public_input.iter()) {
+            *acc += *r * x;
+        }
+    }
+    let combined_inputs = E::G1::msm_unchecked(&pvk.vk.gamma_abc_g1, &input_scalars);
+    let c_points = batch.iter().map(|(_, p)| p.0.c).collect::<Vec<_>>();
+    let combined_c = E::G1::msm_unchecked(&c_points, &coefficients);
+
+    let scaled_a = batch
+        .iter()
+        .zip(&coefficients)
+        .map(|((_, p), r)| p.0.a * r)
+        .collect::<Vec<_>>();
+    let mut g1 = E::G1::normalize_batch(&scaled_a);
+    g1.push(combined_inputs.into_affine());
+    g1.push(combined_c.into_affine());
+    let mut g2 = batch
+        .iter()
+        .map(|(_, p)| E::G2Prepared::from(p.0.b))
+        .collect::<Vec<_>>();
+    g2.push(pvk.gamma_g2_neg_pc.clone());
+    g2.push(pvk.delta_g2_neg_pc.clone());
+
+    let coefficient_sum = coefficients.iter().sum::<E::ScalarField>();
+    let expected = PairingOutput::<E>(pvk.alpha_g1_beta_g2) * coefficient_sum;
+    if E::final_exponentiation(E::multi_miller_loop(g1, g2)) == Some(expected) {
+        return Ok(Vec::new());
+    }
+
+    let mut failed = Vec::new();
+    for (i, (public_input, proof)) in batch.iter().enumerate() {
+        if !verify_proof_with_prepared_key(pvk, public_input, proof)? {
+            failed.push(i);
+        }
+    }
+    Ok(failed)
+}
+
+/// Checks that `vk` expects exactly as many public inputs as were given.
+fn check_public_input<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    public_input: &[E::ScalarField],
+) -> Result<(), Groth16Error> {
+    let expected = vk.gamma_abc_g1.len().saturating_sub(1);
+    if public_input.len() != expected {
+        return Err(Groth16Error::PublicInputMismatch {
+            expected,
+            actual: public_input.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tests for proving and verifying with the sum circuit as an example.
+    use super::*;
+    use crate::circuits::sum::SumCircuit;
+    use crate::circuits::test_vectors::assert_vector;
+    use ark_bn254::Fr;
+    use ark_relations::r1cs::ConstraintSystemRef;
+    use rand_chacha::rand_core::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use std::cell::Cell;
+
+    /// The sum circuit with the witness 10 + 32 = 42.
+    fn sum_circuit() -> SumCircuit<Fr> {
+        SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into()))
+    }
+
+    /// Keys for the sum circuit and a proof that 10 + 32 = 42, drawn from `rng`.
+    fn sum_fixture<R: RngCore + CryptoRng>(
+        rng: &mut R,
+    ) -> (ProvingKey<Bn254>, VerifyingKey<Bn254>, Bn254Proof) {
+        let (pk, vk) = setup_with_rng(SumCircuit::default(), rng).expect("keys created");
+        let proof = generate_proof_with_rng(&pk, sum_circuit(), rng).expect("proof created");
+        (pk, vk, proof)
+    }
+
+    /// A sum circuit that is not `Clone` and counts how often it is synthesized.
+    struct CountedSum<'a> {
+        circuit: SumCircuit<Fr>,
+        synthesized: &'a Cell<usize>,
+    }
+
+    impl ConstraintSynthesizer<Fr> for CountedSum<'_> {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            self.synthesized.set(self.synthesized.get() + 1);
+            self.circuit.generate_constraints(cs)
+        }
+    }
+
+    /// Test that a circuit need not be `Clone` to be proven, and is synthesized once.
+    #[test]
+    fn prove_synthesizes_once() {
+        let (pk, vk) = setup::<Bn254, _>(SumCircuit::<Fr>::default()).expect("keys created");
+
+        let synthesized = Cell::new(0);
+        let circuit = CountedSum {
+            circuit: sum_circuit(),
+            synthesized: &synthesized,
+        };
+        let proof = generate_proof(pk, circuit).expect("proof created");
+        assert_eq!(synthesized.get(), 1);
+
+        let verified = verify_proof(vk, &[42.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    /// Test that the phased prover reports every phase in order and produces a proof
+    /// that verifies.
+    #[test]
+    fn prove_with_progress() {
+        let (pk, vk, _) = sum_fixture(&mut thread_rng());
+
+        let mut phases = Vec::new();
+        let proof = generate_proof_with_progress(&pk, sum_circuit(), |phase| {
+            phases.push(phase);
+            true
+        })
+        .expect("proof created");
+
+        assert_eq!(
+            phases,
+            [
+                ProvingPhase::Synthesis,
+                ProvingPhase::WitnessMap,
+                ProvingPhase::MsmH,
+                ProvingPhase::MsmL,
+                ProvingPhase::MsmA,
+                ProvingPhase::MsmBG1,
+                ProvingPhase::MsmBG2,
+            ]
+        );
+        let verified = verify_proof(vk, &[42.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    /// Test that the phased prover stops at the first phase the callback refuses.
+    #[test]
+    fn prove_cancelled() {
+        let (pk, _, _) = sum_fixture(&mut thread_rng());
+
+        let mut phases = Vec::new();
+        let result = generate_proof_with_progress(&pk, sum_circuit(), |phase| {
+            phases.push(phase);
+            phase != ProvingPhase::WitnessMap
+        });
+
+        assert!(
+            matches!(result, Err(Groth16Error::Cancelled)),
+            "a cancelled proof can't be created"
+        );
+        assert_eq!(phases, [ProvingPhase::Synthesis, ProvingPhase::WitnessMap]);
+    }
+
+    /// Test that verification reports a wrong number of public inputs as an error rather
+    /// than a failed proof.
+    #[test]
+    fn verify_wrong_public_input_count() {
+        let (_, vk, proof) = sum_fixture(&mut thread_rng());
+
+        let result = verify_proof(vk, &[42.into(), 42.into()], proof);
+        assert!(matches!(
+            result,
+            Err(Groth16Error::PublicInputMismatch {
+                expected: 1,
+                actual: 2
+            })
+        ));
+    }
+
+    /// Test that proofs round-trip in every serialization mode, that compression halves
+    /// their size, and that only checked decoding rejects a point off the curve.
+    #[test]
+    fn serialize_proof_modes() {
+        let (_, vk, proof) = sum_fixture(&mut thread_rng());
+
+        let uncompressed = proof.0.try_to_bytes().expect("proof serialized");
+        let compressed = proof
+            .0
+            .try_to_bytes_with_mode(SerializationMode::COMPRESSED)
+            .expect("proof serialized");
+        assert_eq!(compressed.len() * 2, uncompressed.len());
+
+        for (bytes, mode) in [
+            (&uncompressed, SerializationMode::UNCOMPRESSED),
+            (&uncompressed, SerializationMode::UNCOMPRESSED.unchecked()),
+            (&compressed, SerializationMode::COMPRESSED),
+            (&compressed, SerializationMode::COMPRESSED.unchecked()),
+        ] {
+            let decoded: Bn254Proof = decode_with_mode::<Proof<Bn254>>(bytes.clone(), mode)
+                .expect("proof deserialized")
+                .into();
+            assert_eq!(decoded, proof);
+        }
+        assert!(decode_with_mode::<Proof<Bn254>>(
+            compressed.clone(),
+            SerializationMode::UNCOMPRESSED
+        )
+        .is_err());
+
+        // Moving `A` off the curve is only caught by checked decoding.
+        let mut tampered = uncompressed.clone();
+        tampered[0] ^= 1;
+        assert!(matches!(
+            decode_with_mode::<Proof<Bn254>>(tampered.clone(), SerializationMode::UNCOMPRESSED),
+            Err(Groth16Error::Deserialization(_))
+        ));
+        assert!(decode_with_mode::<Proof<Bn254>>(
+            tampered,
+            SerializationMode::UNCOMPRESSED.unchecked()
+        )
+        .is_ok());
+
+        let verified = verify_proof(vk, &[42.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    /// Test that a batch of valid proofs passes and that a batch with invalid proofs
+    /// reports exactly those.
+    #[test]
+    fn batch_verify() {
+        let (pk, vk, _) = sum_fixture(&mut thread_rng());
+        let pvk = prepare_verifying_key(&vk);
+
+        let sums = [(1u64, 2u64), (10, 32), (100, 200), (7, 8)];
+        let proofs = sums
+            .iter()
+            .map(|&(a, b)| {
+                generate_proof_with_key(
+                    &pk,
+                    SumCircuit::new(Some(a.into()), Some(b.into()), Some((a + b).into())),
+                )
+                .expect("proof created")
+            })
+            .collect::<Vec<_>>();
+        let mut public_inputs = sums
+            .iter()
+            .map(|&(a, b)| vec![Fr::from(a + b)])
+            .collect::<Vec<_>>();
+
+        let batch = |public_inputs: &[Vec<Fr>]| {
+            let batch = public_inputs
+                .iter()
+                .zip(&proofs)
+                .map(|(x, p)| (x.as_slice(), p))
+                .collect::<Vec<_>>();
+            verify_proofs_batch(&pvk, &batch).expect("batch is verified")
+        };
+        assert!(batch(&public_inputs).is_empty(), "every proof is valid");
+
+        public_inputs[1] = vec![Fr::from(43u64)];
+        public_inputs[3] = vec![Fr::from(16u64)];
+        assert_eq!(batch(&public_inputs), [1, 3]);
+
+        assert!(matches!(
+            verify_proofs_batch(&pvk, &[(&[][..], &proofs[0])]),
+            Err(Groth16Error::PublicInputMismatch { .. })
+        ));
+    }
+
+    /// Test that a fixed seed reproduces the keys and proof in `tests/vectors`.
+    #[test]
+    fn deterministic_vectors() {
+        let (pk, vk, proof) = sum_fixture(&mut ChaCha20Rng::seed_from_u64(42));
+
+        let pk_bytes = pk.try_to_bytes().expect("proving key serialized");
+        assert_vector("sum_proving_key.sha256", &Sha256::digest(pk_bytes));
+        assert_vector(
+            "sum_verifying_key",
+            &vk.try_to_bytes().expect("key serialized"),
+        );
+        assert_vector(
+            "sum_proof",
+            &proof.0.try_to_bytes().expect("proof serialized"),
+        );
+
+        let verified = verify_proof(vk, &[42.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
 }
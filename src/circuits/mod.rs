@@ -8,8 +8,47 @@
 //! * `sum`: A circuit that proves knowledge of two private numbers that sum to a public value
 //! * `compare`: A circuit that proves a longer string starts with a shorter string
 
+/// Aggregation of Groth16 proofs under one verifying key
+pub mod aggregation;
+/// Multi-party trusted setup ceremony for Groth16 keys
+pub mod ceremony;
 /// Circuit for string prefix comparison proofs
 pub mod compare;
+/// Self-describing container format for keys and proofs
+pub mod container;
 pub mod groth16;
+/// Reader for snarkjs powers-of-tau files
+pub mod ptau;
+/// snarkjs JSON files for proofs, verifying keys and public inputs
+pub mod snarkjs;
+/// Solidity verifier contracts and calldata for BN254 proofs
+pub mod solidity;
 /// Circuit for sum relationship proofs
 pub mod sum;
+
+#[cfg(test)]
+pub(crate) mod test_vectors {
+    //! Golden files for the deterministic test vectors in `tests/vectors`.
+    //!
+    //! Run the tests with `UPDATE_TEST_VECTORS=1` to rewrite the files after an
+    //! intentional change to the keys or proofs.
+    use std::path::PathBuf;
+
+    /// Asserts that `bytes` match the hex-encoded vector stored under `name`.
+    pub(crate) fn assert_vector(name: &str, bytes: &[u8]) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/vectors")
+            .join(format!("{name}.hex"));
+        let actual = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        if std::env::var_os("UPDATE_TEST_VECTORS").is_some() {
+            std::fs::write(&path, format!("{actual}\n")).expect("test vector written");
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).expect("test vector read");
+        assert_eq!(
+            actual,
+            expected.trim(),
+            "{name} differs from its test vector"
+        );
+    }
+}
@@ -0,0 +1,499 @@
+//! # Powers of Tau Files
+//!
+//! This module reads the output of a public powers-of-tau ceremony in the `.ptau` format
+//! written by snarkjs, for BN254. Starting a [`Ceremony`](crate::circuits::ceremony::Ceremony)
+//! from it with `Ceremony::from_powers_of_tau` replaces phase 1, so that only the
+//! circuit-specific phase 2 has to be run.
+//!
+//! A `.ptau` file starts with the magic `ptau`, a version and a number of sections. Each
+//! section has a type, a byte length and a body; the header (type 1) holds the base field
+//! and the power `k`, and types 2 to 6 hold the powers of tau for a domain of `2^k`
+//! elements. Points are stored uncompressed with every coordinate in little-endian
+//! Montgomery form, and the point at infinity as zeros. Other sections, such as the
+//! contribution history, are skipped: the powers are checked for consistency with each
+//! other, but which ceremony produced them is up to the caller to establish, for example
+//! by comparing the file hash against the published one.
+//!
+//! Published files go up to `k = 28` and are many gigabytes, so only the section headers
+//! are read in full. The reader then seeks to each section and decodes just the powers
+//! for the domain the circuit needs.
+//!
+
+use crate::circuits::ceremony::PowersOfTau;
+use crate::circuits::groth16::Groth16Error;
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInt, BigInteger, FftField, Field, PrimeField};
+use ark_serialize::SerializationError;
+use std::io::{Read, Seek, SeekFrom};
+
+const MAGIC: &[u8] = b"ptau";
+const VERSION: u32 = 1;
+
+const HEADER: u32 = 1;
+const TAU_G1: u32 = 2;
+const TAU_G2: u32 = 3;
+const ALPHA_TAU_G1: u32 = 4;
+const BETA_TAU_G1: u32 = 5;
+const BETA_G2: u32 = 6;
+
+/// The size of a base field element.
+const FQ_SIZE: usize = 32;
+/// The size of an uncompressed point in G1.
+const G1_SIZE: usize = 2 * FQ_SIZE;
+/// The size of an uncompressed point in G2.
+const G2_SIZE: usize = 4 * FQ_SIZE;
+
+/// Reads and checks the powers of tau for a domain of `domain_size` elements from a
+/// `.ptau` file, such as the size returned by `ceremony::required_domain_size`.
+///
+/// The domain is rounded up to a power of two of at least two elements, and only its
+/// powers are decoded. Fails with `Deserialization` if the file is not a BN254 `.ptau`
+/// file, with `CircuitTooLarge` if the file holds a smaller domain, and with
+/// `InvalidPowersOfTau` if the points are not consistent powers of the same secrets.
+pub fn read_powers_of_tau<R: Read + Seek>(
+    mut reader: R,
+    domain_size: usize,
+) -> Result<PowersOfTau<Bn254>, Groth16Error> {
+    let sections = sections(&mut reader)?;
+
+    let header = section(&sections, HEADER)?;
+    let mut header = Cursor(&header.read(&mut reader, 0, 4 + FQ_SIZE + 4)?);
+    let field_size = header.u32()? as usize;
+    if field_size != FQ_SIZE || header.take(field_size)? != Fq::MODULUS.to_bytes_le() {
+        return Err(invalid_data());
+    }
+    // No evaluation domain of the scalar field is larger than its two-adicity allows.
+    let power = header.u32()?;
+    if power > Fr::TWO_ADICITY {
+        return Err(invalid_data());
+    }
+    let max = 1usize << power;
+    let n = domain_size.max(2).next_power_of_two();
+    if n > max {
+        return Err(Groth16Error::CircuitTooLarge { max, actual: n });
+    }
+
+    let montgomery = Montgomery::new();
+    let mut g1_points = |kind, total, count| {
+        let body = section(&sections, kind)?.read_points(&mut reader, total, count, G1_SIZE)?;
+        montgomery.g1_points(&body, count)
+    };
+    let tau_g1 = g1_points(TAU_G1, 2 * max - 1, 2 * n - 1)?;
+    let alpha_tau_g1 = g1_points(ALPHA_TAU_G1, max, n)?;
+    let beta_tau_g1 = g1_points(BETA_TAU_G1, max, n)?;
+    let mut g2_points = |kind, total, count| {
+        let body = section(&sections, kind)?.read_points(&mut reader, total, count, G2_SIZE)?;
+        montgomery.g2_points(&body, count)
+    };
+    let powers = PowersOfTau {
+        tau_g1,
+        tau_g2: g2_points(TAU_G2, max, n)?,
+        alpha_tau_g1,
+        beta_tau_g1,
+        beta_g2: g2_points(BETA_G2, 1, 1)?[0],
+    };
+    if !powers.is_well_formed() {
+        return Err(Groth16Error::InvalidPowersOfTau);
+    }
+    Ok(powers)
+}
+
+/// The position of a section body in a `.ptau` file.
+struct Section {
+    kind: u32,
+    offset: u64,
+    size: u64,
+}
+
+impl Section {
+    /// Reads `length` bytes of the body, starting `start` bytes into it.
+    fn read<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        start: u64,
+        length: usize,
+    ) -> Result<Vec<u8>, Groth16Error> {
+        if start + length as u64 > self.size {
+            return Err(invalid_data());
+        }
+        seek(reader, SeekFrom::Start(self.offset + start))?;
+        read_bytes(reader, length)
+    }
+
+    /// Reads the first `count` of the `total` points of `size` bytes in the body.
+    fn read_points<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        total: usize,
+        count: usize,
+        size: usize,
+    ) -> Result<Vec<u8>, Groth16Error> {
+        if self.size != (total * size) as u64 {
+            return Err(invalid_data());
+        }
+        self.read(reader, 0, count * size)
+    }
+}
+
+/// Reads the section headers of a `.ptau` file, seeking past every body.
+fn sections<R: Read + Seek>(reader: &mut R) -> Result<Vec<Section>, Groth16Error> {
+    let length = seek(reader, SeekFrom::End(0))?;
+    seek(reader, SeekFrom::Start(0))?;
+
+    let preamble = read_bytes(reader, MAGIC.len() + 8)?;
+    let mut preamble = Cursor(&preamble);
+    if preamble.take(MAGIC.len())? != MAGIC || preamble.u32()? != VERSION {
+        return Err(invalid_data());
+    }
+    let count = preamble.u32()?;
+    let mut sections = Vec::new();
+    let mut offset = (MAGIC.len() + 8) as u64;
+    for _ in 0..count {
+        let header = read_bytes(reader, 12)?;
+        let mut header = Cursor(&header);
+        let kind = header.u32()?;
+        let size = header.u64()?;
+        offset += 12;
+        if size > length - offset {
+            return Err(Groth16Error::Deserialization(
+                SerializationError::NotEnoughSpace,
+            ));
+        }
+        sections.push(Section { kind, offset, size });
+        offset += size;
+        seek(reader, SeekFrom::Start(offset))?;
+    }
+    Ok(sections)
+}
+
+fn seek<R: Seek>(reader: &mut R, position: SeekFrom) -> Result<u64, Groth16Error> {
+    reader
+        .seek(position)
+        .map_err(|e| Groth16Error::Deserialization(e.into()))
+}
+
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> Result<Vec<u8>, Groth16Error> {
+    let mut bytes = vec![0; length];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| Groth16Error::Deserialization(e.into()))?;
+    Ok(bytes)
+}
+
+fn section(sections: &[Section], kind: u32) -> Result<&Section, Groth16Error> {
+    sections
+        .iter()
+        .find(|section| section.kind == kind)
+        .ok_or_else(invalid_data)
+}
+
+fn invalid_data() -> Groth16Error {
+    Groth16Error::Deserialization(SerializationError::InvalidData)
+}
+
+/// Reads little-endian integers and byte strings from a slice.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Groth16Error> {
+        if self.0.len() < n {
+            return Err(Groth16Error::Deserialization(
+                SerializationError::NotEnoughSpace,
+            ));
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, Groth16Error> {
+        let bytes = self.take(4)?.try_into().expect("four bytes were taken");
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, Groth16Error> {
+        let bytes = self.take(8)?.try_into().expect("eight bytes were taken");
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+/// Decodes points whose coordinates are in Montgomery form, `x * 2^256 mod q`.
+struct Montgomery {
+    r_inverse: Fq,
+}
+
+impl Montgomery {
+    fn new() -> Self {
+        let r = Fq::from(2u64).pow([256]);
+        Self {
+            r_inverse: r.inverse().expect("2^256 is invertible"),
+        }
+    }
+
+    fn fq(&self, bytes: &[u8]) -> Result<Fq, Groth16Error> {
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().expect("chunks have eight bytes"));
+        }
+        let value = Fq::from_bigint(BigInt::new(limbs)).ok_or_else(invalid_data)?;
+        Ok(value * self.r_inverse)
+    }
+
+    fn fq2(&self, bytes: &[u8]) -> Result<Fq2, Groth16Error> {
+        Ok(Fq2::new(
+            self.fq(&bytes[..FQ_SIZE])?,
+            self.fq(&bytes[FQ_SIZE..])?,
+        ))
+    }
+
+    fn g1_points(&self, body: &[u8], count: usize) -> Result<Vec<G1Affine>, Groth16Error> {
+        points(body, count, G1_SIZE, |bytes| {
+            let point =
+                G1Affine::new_unchecked(self.fq(&bytes[..FQ_SIZE])?, self.fq(&bytes[FQ_SIZE..])?);
+            check_point(point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve())?;
+            Ok(point)
+        })
+    }
+
+    fn g2_points(&self, body: &[u8], count: usize) -> Result<Vec<G2Affine>, Groth16Error> {
+        points(body, count, G2_SIZE, |bytes| {
+            let point = G2Affine::new_unchecked(
+                self.fq2(&bytes[..2 * FQ_SIZE])?,
+                self.fq2(&bytes[2 * FQ_SIZE..])?,
+            );
+            check_point(point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve())?;
+            Ok(point)
+        })
+    }
+}
+
+/// Fails unless the decoded point is on the curve and in the prime order subgroup.
+fn check_point(valid: bool) -> Result<(), Groth16Error> {
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid_data())
+    }
+}
+
+/// Decodes `count` points of `size` bytes each, with all zeros for the point at infinity.
+fn points<G: AffineRepr>(
+    body: &[u8],
+    count: usize,
+    size: usize,
+    decode: impl Fn(&[u8]) -> Result<G, Groth16Error>,
+) -> Result<Vec<G>, Groth16Error> {
+    if body.len() != count * size {
+        return Err(invalid_data());
+    }
+    body.chunks_exact(size)
+        .map(|bytes| {
+            if bytes.iter().all(|b| *b == 0) {
+                return Ok(G::zero());
+            }
+            decode(bytes)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tests for reading `.ptau` files, which are mostly written here the way snarkjs
+    //! does; `read_snarkjs_ptau` reads the fixture written by `tests/fixtures/snarkjs`.
+    use super::*;
+    use crate::circuits::ceremony::{required_domain_size, Ceremony};
+    use crate::circuits::compare::CompareCircuit;
+    use crate::circuits::groth16::{generate_proof, verify_proof};
+    use crate::circuits::sum::SumCircuit;
+    use ark_ff::UniformRand;
+    use rand::thread_rng;
+    use std::io;
+    use std::path::PathBuf;
+
+    fn write_fq(bytes: &mut Vec<u8>, x: Fq) {
+        let r = Fq::from(2u64).pow([256]);
+        bytes.extend((x * r).into_bigint().to_bytes_le());
+    }
+
+    fn write_g1(bytes: &mut Vec<u8>, point: &G1Affine) {
+        match point.xy() {
+            Some((x, y)) => [*x, *y].into_iter().for_each(|c| write_fq(bytes, c)),
+            None => bytes.extend([0; 2 * FQ_SIZE]),
+        }
+    }
+
+    fn write_g2(bytes: &mut Vec<u8>, point: &G2Affine) {
+        let (x, y) = point.xy().expect("not the point at infinity");
+        [x.c0, x.c1, y.c0, y.c1]
+            .into_iter()
+            .for_each(|c| write_fq(bytes, c));
+    }
+
+    /// Writes `powers` as a `.ptau` file with an empty contribution history.
+    fn ptau_file(powers: &PowersOfTau<Bn254>) -> Vec<u8> {
+        let mut header = 32u32.to_le_bytes().to_vec();
+        header.extend(Fq::MODULUS.to_bytes_le());
+        header.extend(powers.domain_size().trailing_zeros().to_le_bytes());
+        header.extend(0u32.to_le_bytes());
+
+        let g1_section = |points: &[G1Affine]| {
+            let mut body = Vec::new();
+            points.iter().for_each(|p| write_g1(&mut body, p));
+            body
+        };
+        let g2_section = |points: &[G2Affine]| {
+            let mut body = Vec::new();
+            points.iter().for_each(|p| write_g2(&mut body, p));
+            body
+        };
+        let sections = [
+            (HEADER, header),
+            (TAU_G1, g1_section(&powers.tau_g1)),
+            (TAU_G2, g2_section(&powers.tau_g2)),
+            (ALPHA_TAU_G1, g1_section(&powers.alpha_tau_g1)),
+            (BETA_TAU_G1, g1_section(&powers.beta_tau_g1)),
+            (BETA_G2, g2_section(&[powers.beta_g2])),
+            (7, 0u32.to_le_bytes().to_vec()),
+        ];
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((sections.len() as u32).to_le_bytes());
+        for (kind, body) in sections {
+            bytes.extend(kind.to_le_bytes());
+            bytes.extend((body.len() as u64).to_le_bytes());
+            bytes.extend(body);
+        }
+        bytes
+    }
+
+    /// Powers of tau for a domain of 8 elements, with random secrets.
+    fn powers_of_tau() -> PowersOfTau<Bn254> {
+        let mut rng = thread_rng();
+        let mut powers = PowersOfTau::new(8);
+        powers.apply(Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng));
+        powers
+    }
+
+    /// Test that a `.ptau` file is read back, that its first point is the generator in
+    /// Montgomery form as snarkjs writes it, and that its keys prove and verify.
+    #[test]
+    fn read_ptau_prove_verify_sum() {
+        let powers = powers_of_tau();
+        let bytes = ptau_file(&powers);
+        // The x coordinate of the generator of G1 is 1, so 2^256 mod q.
+        let one = "9d0d8fc58d435dd33d0bc7f528eb780a2c4679786fa36e662fdf079ac1770a0e";
+        let offset = MAGIC.len() + 8 + 12 + 44 + 12;
+        let encoded = bytes[offset..offset + 32]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        assert_eq!(encoded, one);
+
+        let n = required_domain_size(SumCircuit::<Fr>::default()).expect("domain sized");
+        let read = read_powers_of_tau(io::Cursor::new(&bytes), n).expect("powers read");
+        assert_eq!(read, powers.truncate(n).expect("powers truncated"));
+
+        let mut ceremony =
+            Ceremony::from_powers_of_tau(&read, SumCircuit::default()).expect("ceremony started");
+        ceremony.contribute(&mut thread_rng()).expect("contributed");
+        ceremony
+            .verify_with_powers_of_tau(&read, SumCircuit::default())
+            .expect("ceremony is verified");
+        assert!(matches!(
+            ceremony.verify_with_powers_of_tau(&powers_of_tau(), SumCircuit::default()),
+            Err(Groth16Error::InvalidPowersOfTau)
+        ));
+
+        let (pk, vk) = ceremony.keys().expect("keys available");
+        let proof = generate_proof(
+            pk,
+            SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into())),
+        )
+        .expect("proof created");
+        let verified = verify_proof(vk, &[42.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    /// Test that a smaller domain is read from the start of each section, without
+    /// decoding the points past it.
+    #[test]
+    fn read_ptau_prefix() {
+        let powers = powers_of_tau();
+        let mut bytes = ptau_file(&powers);
+        // Corrupt the last point of the tau section in G1, which a domain of 4 doesn't use.
+        let offset = MAGIC.len() + 8 + 12 + 44 + 12 + 14 * G1_SIZE;
+        bytes[offset..offset + G1_SIZE].fill(0xff);
+
+        let read = read_powers_of_tau(io::Cursor::new(&bytes), 3).expect("powers read");
+        assert_eq!(read, powers.truncate(4).expect("powers truncated"));
+        assert!(matches!(
+            read_powers_of_tau(io::Cursor::new(&bytes), 8),
+            Err(Groth16Error::Deserialization(_))
+        ));
+        assert!(matches!(
+            read_powers_of_tau(io::Cursor::new(&bytes), 9),
+            Err(Groth16Error::CircuitTooLarge { max: 8, actual: 16 })
+        ));
+    }
+
+    /// Test that a file in the snarkjs layout, with a contribution history, is read and
+    /// sets up working keys.
+    #[test]
+    fn read_snarkjs_ptau() {
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/snarkjs/pot4_0001.ptau");
+        let bytes = std::fs::read(path).expect("fixture read");
+        let n = required_domain_size(SumCircuit::<Fr>::default()).expect("domain sized");
+        let powers = read_powers_of_tau(io::Cursor::new(&bytes), n).expect("powers read");
+        assert!(matches!(
+            read_powers_of_tau(io::Cursor::new(&bytes), 17),
+            Err(Groth16Error::CircuitTooLarge {
+                max: 16,
+                actual: 32
+            })
+        ));
+
+        let mut ceremony =
+            Ceremony::from_powers_of_tau(&powers, SumCircuit::default()).expect("ceremony started");
+        ceremony.contribute(&mut thread_rng()).expect("contributed");
+        let (pk, vk) = ceremony.keys().expect("keys available");
+        let proof = generate_proof(
+            pk,
+            SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into())),
+        )
+        .expect("proof created");
+        let verified = verify_proof(vk, &[42.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    /// Test that malformed and inconsistent files, and circuits that are too large, are
+    /// rejected.
+    #[test]
+    fn read_ptau_rejects_invalid_files() {
+        let mut powers = powers_of_tau();
+        let bytes = ptau_file(&powers);
+        assert!(matches!(
+            read_powers_of_tau(io::Cursor::new(&bytes[..bytes.len() - 1]), 8),
+            Err(Groth16Error::Deserialization(_))
+        ));
+        assert!(matches!(
+            read_powers_of_tau(io::Cursor::new(b"zkey"), 8),
+            Err(Groth16Error::Deserialization(_))
+        ));
+
+        powers.tau_g1.swap(2, 3);
+        assert!(matches!(
+            read_powers_of_tau(io::Cursor::new(ptau_file(&powers)), 8),
+            Err(Groth16Error::InvalidPowersOfTau)
+        ));
+
+        let powers = powers_of_tau();
+        let circuit = CompareCircuit::<Fr>::with_prefix_length(8);
+        assert!(matches!(
+            Ceremony::from_powers_of_tau(&powers, circuit),
+            Err(Groth16Error::CircuitTooLarge { max: 8, .. })
+        ));
+    }
+}
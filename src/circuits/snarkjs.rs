@@ -0,0 +1,346 @@
+//! # snarkjs JSON
+//!
+//! This module converts BN254 proofs, verifying keys and public inputs to and from the
+//! `proof.json`, `verification_key.json` and `public.json` files of snarkjs.
+//!
+//! Field elements are decimal strings. Points are in projective coordinates with `z` equal
+//! to one, or `(0, 1, 0)` for the point at infinity, and each coordinate in G2 is a
+//! `[c0, c1]` pair. Importing rejects non-canonical numbers and points outside the prime
+//! order subgroup, so importing what was exported yields a value whose `TrySerializer`
+//! encoding is byte for byte the one it was exported from.
+//!
+//! The `vk_alphabeta_12` field of a verifying key is `e(alpha, beta)`, which snarkjs
+//! writes for older verifiers. It is written on export but ignored on import: verifying
+//! only uses `vk_alpha_1` and `vk_beta_2`, so a wrong value can't affect the result.
+//!
+
+use crate::circuits::groth16::{Bn254Proof, Groth16Error};
+use ark_bn254::{Bn254, Fq, Fq12, Fq2, Fq6, Fr, G1Affine, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_ff::{One, PrimeField, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::SerializationError;
+use serde::{Deserialize, Serialize};
+
+const PROTOCOL: &str = "groth16";
+/// The name snarkjs uses for BN254.
+const CURVE: &str = "bn128";
+
+type G1Json = [String; 3];
+type G2Json = [[String; 2]; 3];
+
+#[derive(Serialize, Deserialize)]
+struct ProofJson {
+    pi_a: G1Json,
+    pi_b: G2Json,
+    pi_c: G1Json,
+    protocol: String,
+    curve: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VerifyingKeyJson {
+    protocol: String,
+    curve: String,
+    #[serde(rename = "nPublic")]
+    n_public: usize,
+    vk_alpha_1: G1Json,
+    vk_beta_2: G2Json,
+    vk_gamma_2: G2Json,
+    vk_delta_2: G2Json,
+    /// `e(alpha, beta)`, which snarkjs writes for older verifiers. It is derived from the
+    /// other fields, so it is not read back.
+    #[serde(rename = "vk_alphabeta_12", default, skip_deserializing)]
+    vk_alphabeta_12: Vec<Vec<[String; 2]>>,
+    #[serde(rename = "IC")]
+    ic: Vec<G1Json>,
+}
+
+/// Writes `proof` as a snarkjs `proof.json`.
+pub fn proof_to_json(proof: &Bn254Proof) -> String {
+    to_json(&ProofJson {
+        pi_a: g1_to_json(&proof.0.a),
+        pi_b: g2_to_json(&proof.0.b),
+        pi_c: g1_to_json(&proof.0.c),
+        protocol: PROTOCOL.to_string(),
+        curve: CURVE.to_string(),
+    })
+}
+
+/// Reads a snarkjs `proof.json`.
+pub fn proof_from_json(json: &str) -> Result<Bn254Proof, Groth16Error> {
+    let proof: ProofJson = from_json(json)?;
+    check_protocol(&proof.protocol, &proof.curve)?;
+    Ok(Proof {
+        a: g1_from_json(&proof.pi_a)?,
+        b: g2_from_json(&proof.pi_b)?,
+        c: g1_from_json(&proof.pi_c)?,
+    }
+    .into())
+}
+
+/// Writes `vk` as a snarkjs `verification_key.json`.
+pub fn verifying_key_to_json(vk: &VerifyingKey<Bn254>) -> String {
+    let alpha_beta = Bn254::pairing(vk.alpha_g1, vk.beta_g2).0;
+    to_json(&VerifyingKeyJson {
+        protocol: PROTOCOL.to_string(),
+        curve: CURVE.to_string(),
+        n_public: vk.gamma_abc_g1.len().saturating_sub(1),
+        vk_alpha_1: g1_to_json(&vk.alpha_g1),
+        vk_beta_2: g2_to_json(&vk.beta_g2),
+        vk_gamma_2: g2_to_json(&vk.gamma_g2),
+        vk_delta_2: g2_to_json(&vk.delta_g2),
+        vk_alphabeta_12: fq12_to_json(&alpha_beta),
+        ic: vk.gamma_abc_g1.iter().map(g1_to_json).collect(),
+    })
+}
+
+/// Reads a snarkjs `verification_key.json`, ignoring its `vk_alphabeta_12`.
+pub fn verifying_key_from_json(json: &str) -> Result<VerifyingKey<Bn254>, Groth16Error> {
+    let vk: VerifyingKeyJson = from_json(json)?;
+    check_protocol(&vk.protocol, &vk.curve)?;
+    if vk.ic.len() != vk.n_public + 1 {
+        return Err(invalid_data());
+    }
+    Ok(VerifyingKey {
+        alpha_g1: g1_from_json(&vk.vk_alpha_1)?,
+        beta_g2: g2_from_json(&vk.vk_beta_2)?,
+        gamma_g2: g2_from_json(&vk.vk_gamma_2)?,
+        delta_g2: g2_from_json(&vk.vk_delta_2)?,
+        gamma_abc_g1: vk.ic.iter().map(g1_from_json).collect::<Result<_, _>>()?,
+    })
+}
+
+/// Writes `public_inputs` as a snarkjs `public.json`.
+pub fn public_inputs_to_json(public_inputs: &[Fr]) -> String {
+    to_json(&public_inputs.iter().map(decimal).collect::<Vec<_>>())
+}
+
+/// Reads a snarkjs `public.json`.
+pub fn public_inputs_from_json(json: &str) -> Result<Vec<Fr>, Groth16Error> {
+    let public_inputs: Vec<String> = from_json(json)?;
+    public_inputs.iter().map(|x| parse(x)).collect()
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).expect("string fields always serialize")
+}
+
+fn from_json<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, Groth16Error> {
+    serde_json::from_str(json).map_err(|_| invalid_data())
+}
+
+fn check_protocol(protocol: &str, curve: &str) -> Result<(), Groth16Error> {
+    if protocol == PROTOCOL && curve == CURVE {
+        Ok(())
+    } else {
+        Err(invalid_data())
+    }
+}
+
+fn invalid_data() -> Groth16Error {
+    Groth16Error::Deserialization(SerializationError::InvalidData)
+}
+
+fn decimal<F: PrimeField>(x: &F) -> String {
+    x.into_bigint().to_string()
+}
+
+/// Parses a decimal string, rejecting anything but the canonical form of an element.
+fn parse<F: PrimeField>(s: &str) -> Result<F, Groth16Error> {
+    let x = F::from_str(s).map_err(|_| invalid_data())?;
+    if decimal(&x) == s {
+        Ok(x)
+    } else {
+        Err(invalid_data())
+    }
+}
+
+fn fq2_to_json(x: &Fq2) -> [String; 2] {
+    [decimal(&x.c0), decimal(&x.c1)]
+}
+
+fn fq2_from_json(x: &[String; 2]) -> Result<Fq2, Groth16Error> {
+    Ok(Fq2::new(parse(&x[0])?, parse(&x[1])?))
+}
+
+fn fq12_to_json(x: &Fq12) -> Vec<Vec<[String; 2]>> {
+    let fq6 = |x: &Fq6| vec![fq2_to_json(&x.c0), fq2_to_json(&x.c1), fq2_to_json(&x.c2)];
+    vec![fq6(&x.c0), fq6(&x.c1)]
+}
+
+fn g1_to_json(point: &G1Affine) -> G1Json {
+    match point.xy() {
+        Some((x, y)) => [decimal(x), decimal(y), decimal(&Fq::one())],
+        None => [Fq::zero(), Fq::one(), Fq::zero()].map(|c| decimal(&c)),
+    }
+}
+
+fn g1_from_json(point: &G1Json) -> Result<G1Affine, Groth16Error> {
+    let [x, y, z] = [&point[0], &point[1], &point[2]].map(|c| parse::<Fq>(c));
+    let (x, y, z) = (x?, y?, z?);
+    if z.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    let point = G1Affine::new_unchecked(x, y);
+    if !z.is_one() || !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid_data());
+    }
+    Ok(point)
+}
+
+fn g2_to_json(point: &G2Affine) -> G2Json {
+    match point.xy() {
+        Some((x, y)) => [fq2_to_json(x), fq2_to_json(y), fq2_to_json(&Fq2::one())],
+        None => [Fq2::zero(), Fq2::one(), Fq2::zero()].map(|c| fq2_to_json(&c)),
+    }
+}
+
+fn g2_from_json(point: &G2Json) -> Result<G2Affine, Groth16Error> {
+    let [x, y, z] = [&point[0], &point[1], &point[2]].map(fq2_from_json);
+    let (x, y, z) = (x?, y?, z?);
+    if z.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    let point = G2Affine::new_unchecked(x, y);
+    if !z.is_one() || !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid_data());
+    }
+    Ok(point)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tests for the snarkjs JSON files, using a sum circuit proof.
+    use super::*;
+    use crate::circuits::groth16::{
+        decode_with_mode, generate_proof, setup, verify_proof, SerializationMode, TrySerializer,
+    };
+    use crate::circuits::sum::SumCircuit;
+    use std::path::PathBuf;
+
+    fn read_fixture(name: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/snarkjs")
+            .join(name);
+        std::fs::read_to_string(path).expect("fixture read")
+    }
+
+    /// Test that the fixture proof, verifying key and public inputs for 33 are read and
+    /// verify, and that exporting them again gives the same JSON, `vk_alphabeta_12`
+    /// included.
+    #[test]
+    fn read_snarkjs_files() {
+        let proof_json = read_fixture("proof.json");
+        let vk_json = read_fixture("verification_key.json");
+        let public_json = read_fixture("public.json");
+
+        let proof = proof_from_json(&proof_json).expect("proof read");
+        let vk = verifying_key_from_json(&vk_json).expect("key read");
+        let public_inputs = public_inputs_from_json(&public_json).expect("inputs read");
+        assert_eq!(public_inputs, vec![Fr::from(33u64)]);
+        let verified = verify_proof(vk.clone(), &public_inputs, proof.clone()).expect("verified");
+        assert!(verified, "this can't be verified");
+        let verified =
+            verify_proof(vk.clone(), &[Fr::from(34u64)], proof.clone()).expect("verified");
+        assert!(!verified, "this should not be verified");
+
+        let value = |json: &str| serde_json::from_str::<serde_json::Value>(json).expect("JSON");
+        assert_eq!(value(&proof_to_json(&proof)), value(&proof_json));
+        // The pairing is computed on export, as the fixture doesn't pass it through.
+        let exported = value(&verifying_key_to_json(&vk));
+        assert_eq!(
+            exported["vk_alphabeta_12"],
+            value(&vk_json)["vk_alphabeta_12"]
+        );
+        assert_eq!(exported, value(&vk_json));
+    }
+
+    /// Test that the proof, verifying key and public inputs survive a round trip through
+    /// JSON with the same binary encoding, and still verify.
+    #[test]
+    fn json_round_trip_sum() {
+        let (pk, vk) = setup::<Bn254, _>(SumCircuit::default()).expect("keys created");
+        let proof = generate_proof(
+            pk,
+            SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into())),
+        )
+        .expect("proof created");
+        let public_inputs = vec![Fr::from(42u64)];
+
+        let proof_json = proof_to_json(&proof);
+        let vk_json = verifying_key_to_json(&vk);
+        let public_json = public_inputs_to_json(&public_inputs);
+        assert_eq!(public_json, "[\n  \"42\"\n]");
+
+        let read_proof = proof_from_json(&proof_json).expect("proof read");
+        let read_vk = verifying_key_from_json(&vk_json).expect("key read");
+        let read_inputs = public_inputs_from_json(&public_json).expect("inputs read");
+        assert_eq!(
+            read_proof.0.try_to_bytes().expect("serialized"),
+            proof.0.try_to_bytes().expect("serialized")
+        );
+        assert_eq!(
+            read_vk.try_to_bytes().expect("serialized"),
+            vk.try_to_bytes().expect("serialized")
+        );
+        assert_eq!(read_inputs, public_inputs);
+
+        // And back from binary, so both encodings describe the same values.
+        let bytes = read_vk.try_to_bytes().expect("serialized");
+        let decoded: VerifyingKey<Bn254> =
+            decode_with_mode(bytes, SerializationMode::UNCOMPRESSED).expect("deserialized");
+        assert_eq!(verifying_key_to_json(&decoded), vk_json);
+
+        let verified = verify_proof(read_vk, &read_inputs, read_proof).expect("verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    /// Test the layout of a known proof, including the point at infinity.
+    #[test]
+    fn json_layout() {
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        let proof: Bn254Proof = Proof {
+            a: g1,
+            b: g2,
+            c: G1Affine::zero(),
+        }
+        .into();
+        let json: serde_json::Value =
+            serde_json::from_str(&proof_to_json(&proof)).expect("valid JSON");
+        assert_eq!(json["pi_a"], serde_json::json!(["1", "2", "1"]));
+        assert_eq!(json["pi_c"], serde_json::json!(["0", "1", "0"]));
+        assert_eq!(json["pi_b"][2], serde_json::json!(["1", "0"]));
+        assert_eq!(json["protocol"], "groth16");
+        assert_eq!(json["curve"], "bn128");
+        assert_eq!(
+            proof_from_json(&json.to_string()).expect("proof read"),
+            proof
+        );
+    }
+
+    /// Test that non-canonical numbers, points off the curve and other curves are rejected.
+    #[test]
+    fn json_rejects_invalid_values() {
+        let modulus = Fr::MODULUS.to_string();
+        assert!(public_inputs_from_json(&format!("[\"{modulus}\"]")).is_err());
+        assert!(public_inputs_from_json("[\"042\"]").is_err());
+        assert!(public_inputs_from_json("[42]").is_err());
+
+        let proof: Bn254Proof = Proof {
+            a: G1Affine::generator(),
+            b: G2Affine::generator(),
+            c: G1Affine::generator(),
+        }
+        .into();
+        let json = proof_to_json(&proof);
+        let off_curve = json.replacen("\"2\"", "\"3\"", 1);
+        assert!(matches!(
+            proof_from_json(&off_curve),
+            Err(Groth16Error::Deserialization(_))
+        ));
+        assert!(proof_from_json(&json.replace("bn128", "bls12381")).is_err());
+    }
+}
@@ -0,0 +1,495 @@
+//! # Solidity Verifier
+//!
+//! This module generates Ethereum verifier contracts for BN254 verifying keys, and encodes
+//! proofs as calldata for them.
+//!
+//! `verifier_contract` writes a self-contained Solidity contract with the key embedded as
+//! constants. It checks proofs with the `ecAdd` (0x06), `ecMul` (0x07) and pairing (0x08)
+//! precompiles, so it needs no libraries. The contract exposes
+//!
+//! ```text
+//! verifyProof(uint256[2] a, uint256[2][2] b, uint256[2] c, uint256[n] input) returns (bool)
+//! ```
+//!
+//! where `n` is the number of public inputs, and the parameter is left out when there are
+//! none. As in the contracts generated by snarkjs, each coordinate of `b` is written
+//! `[c1, c0]`, the order the pairing precompile expects. `encode_calldata` writes the call.
+//!
+
+use crate::circuits::groth16::{Bn254Proof, Groth16Error};
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_groth16::VerifyingKey;
+use ark_relations::r1cs::SynthesisError;
+use std::fmt::Write;
+use tiny_keccak::{Hasher, Keccak};
+
+const EC_ADD: u8 = 0x06;
+const EC_MUL: u8 = 0x07;
+const PAIRING: u8 = 0x08;
+
+/// Writes a Solidity contract `Groth16Verifier` that verifies proofs for `vk`.
+///
+/// Fails if `vk` has no input commitments, as every key has one for the constant input.
+pub fn verifier_contract(vk: &VerifyingKey<Bn254>) -> Result<String, Groth16Error> {
+    let inputs = vk
+        .gamma_abc_g1
+        .len()
+        .checked_sub(1)
+        .ok_or(Groth16Error::Synthesis(
+            SynthesisError::MalformedVerifyingKey,
+        ))?;
+    let mut s = String::new();
+    let mut line = |text: &str| {
+        s.push_str(text);
+        s.push('\n');
+    };
+
+    line("// SPDX-License-Identifier: MIT");
+    line("pragma solidity ^0.8.0;");
+    line("");
+    line("/// @title Groth16 verifier");
+    line("/// @notice Verifies BN254 Groth16 proofs for a single verifying key.");
+    line("/// @dev Generated by trivial-circuits. Do not edit.");
+    line("contract Groth16Verifier {");
+    line("    // Scalar field modulus");
+    line(&constant("R", &Fr::MODULUS.to_string()));
+    line("    // Base field modulus");
+    line(&constant("Q", &Fq::MODULUS.to_string()));
+    line("");
+    line("    // Verifying key. G2 coordinates are split into c1 and c0.");
+    let g1 = ["X", "Y"];
+    let g2 = ["X1", "X0", "Y1", "Y0"];
+    constants(&mut line, "ALPHA", &g1, &g1_coordinates(&vk.alpha_g1));
+    constants(&mut line, "BETA", &g2, &g2_coordinates(&vk.beta_g2));
+    constants(&mut line, "GAMMA", &g2, &g2_coordinates(&vk.gamma_g2));
+    constants(&mut line, "DELTA", &g2, &g2_coordinates(&vk.delta_g2));
+    for (i, point) in vk.gamma_abc_g1.iter().enumerate() {
+        constants(&mut line, &format!("IC{i}"), &g1, &g1_coordinates(point));
+    }
+    line("");
+    line("    /// @notice Returns whether `(a, b, c)` is a valid proof for the public inputs.");
+    line("    /// @dev `b` is `[[x.c1, x.c0], [y.c1, y.c0]]`.");
+    line("    function verifyProof(");
+    line("        uint256[2] calldata a,");
+    line("        uint256[2][2] calldata b,");
+    if inputs == 0 {
+        line("        uint256[2] calldata c");
+    } else {
+        line("        uint256[2] calldata c,");
+        line(&format!("        uint256[{inputs}] calldata input"));
+    }
+    line("    ) public view returns (bool) {");
+    line("        if (a[0] >= Q || a[1] >= Q) {");
+    line("            return false;");
+    line("        }");
+    if inputs > 0 {
+        line(&format!(
+            "        for (uint256 i = 0; i < {inputs}; i++) {{"
+        ));
+        line("            if (input[i] >= R) {");
+        line("                return false;");
+        line("            }");
+        line("        }");
+    }
+    line("");
+    line("        uint256[2] memory x = [IC0_X, IC0_Y];");
+    for i in 1..=inputs {
+        line(&format!(
+            "        x = ecAdd(x, ecMul([IC{i}_X, IC{i}_Y], input[{}]));",
+            i - 1
+        ));
+    }
+    line("");
+    line("        // e(-A, B) * e(alpha, beta) * e(x, gamma) * e(C, delta) == 1");
+    line("        uint256[24] memory p;");
+    let words = [
+        "a[0]",
+        "(Q - a[1]) % Q",
+        "b[0][0]",
+        "b[0][1]",
+        "b[1][0]",
+        "b[1][1]",
+        "ALPHA_X",
+        "ALPHA_Y",
+        "BETA_X1",
+        "BETA_X0",
+        "BETA_Y1",
+        "BETA_Y0",
+        "x[0]",
+        "x[1]",
+        "GAMMA_X1",
+        "GAMMA_X0",
+        "GAMMA_Y1",
+        "GAMMA_Y0",
+        "c[0]",
+        "c[1]",
+        "DELTA_X1",
+        "DELTA_X0",
+        "DELTA_Y1",
+        "DELTA_Y0",
+    ];
+    for (i, word) in words.iter().enumerate() {
+        line(&format!("        p[{i}] = {word};"));
+    }
+    line("        uint256[1] memory out;");
+    line("        bool ok;");
+    line("        assembly {");
+    line(&format!(
+        "            ok := staticcall(gas(), {PAIRING}, p, 768, out, 32)"
+    ));
+    line("        }");
+    line("        return ok && out[0] == 1;");
+    line("    }");
+    line("");
+    line("    function ecAdd(uint256[2] memory p1, uint256[2] memory p2)");
+    line("        internal");
+    line("        view");
+    line("        returns (uint256[2] memory r)");
+    line("    {");
+    line("        uint256[4] memory args = [p1[0], p1[1], p2[0], p2[1]];");
+    line("        bool ok;");
+    line("        assembly {");
+    line(&format!(
+        "            ok := staticcall(gas(), {EC_ADD}, args, 128, r, 64)"
+    ));
+    line("        }");
+    line("        require(ok, \"ecAdd failed\");");
+    line("    }");
+    line("");
+    line("    function ecMul(uint256[2] memory p, uint256 scalar)");
+    line("        internal");
+    line("        view");
+    line("        returns (uint256[2] memory r)");
+    line("    {");
+    line("        uint256[3] memory args = [p[0], p[1], scalar];");
+    line("        bool ok;");
+    line("        assembly {");
+    line(&format!(
+        "            ok := staticcall(gas(), {EC_MUL}, args, 96, r, 64)"
+    ));
+    line("        }");
+    line("        require(ok, \"ecMul failed\");");
+    line("    }");
+    line("}");
+    Ok(s)
+}
+
+/// Encodes a call to `verifyProof` on a verifier contract for `proof` and `public_inputs`.
+pub fn encode_calldata(proof: &Bn254Proof, public_inputs: &[Fr]) -> Vec<u8> {
+    let mut calldata = Vec::with_capacity(calldata_len(public_inputs.len()));
+    calldata.extend(selector(public_inputs.len()));
+    g1_words(&proof.0.a)
+        .iter()
+        .chain(&g2_words(&proof.0.b))
+        .chain(&g1_words(&proof.0.c))
+        .chain(&public_inputs.iter().map(field_word).collect::<Vec<_>>())
+        .for_each(|w| calldata.extend(w));
+    calldata
+}
+
+/// The selector of `verifyProof` for `inputs` public inputs.
+fn selector(inputs: usize) -> [u8; 4] {
+    let mut signature = "verifyProof(uint256[2],uint256[2][2],uint256[2]".to_string();
+    if inputs > 0 {
+        write!(signature, ",uint256[{inputs}]").expect("writing to a string");
+    }
+    signature.push(')');
+    let mut hash = [0; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(signature.as_bytes());
+    keccak.finalize(&mut hash);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn calldata_len(inputs: usize) -> usize {
+    4 + 32 * (8 + inputs)
+}
+
+fn word(bytes: &[u8]) -> [u8; 32] {
+    let mut word = [0; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    word
+}
+
+fn field_word<F: PrimeField>(x: &F) -> [u8; 32] {
+    word(&x.into_bigint().to_bytes_be())
+}
+
+/// `(x, y)`, with the point at infinity as `(0, 0)` like the precompiles.
+fn g1_coordinates(point: &G1Affine) -> Vec<Fq> {
+    match point.xy() {
+        Some((x, y)) => vec![*x, *y],
+        None => vec![Fq::zero(); 2],
+    }
+}
+
+/// `(x.c1, x.c0, y.c1, y.c0)`, with the point at infinity as zeros like the precompile.
+fn g2_coordinates(point: &G2Affine) -> Vec<Fq> {
+    match point.xy() {
+        Some((x, y)) => vec![x.c1, x.c0, y.c1, y.c0],
+        None => vec![Fq::zero(); 4],
+    }
+}
+
+fn g1_words(point: &G1Affine) -> Vec<[u8; 32]> {
+    g1_coordinates(point).iter().map(field_word).collect()
+}
+
+fn g2_words(point: &G2Affine) -> Vec<[u8; 32]> {
+    g2_coordinates(point).iter().map(field_word).collect()
+}
+
+fn constant(name: &str, value: &str) -> String {
+    format!("    uint256 constant {name} = {value};")
+}
+
+fn constants(line: &mut impl FnMut(&str), name: &str, suffixes: &[&str], coordinates: &[Fq]) {
+    for (suffix, x) in suffixes.iter().zip(coordinates) {
+        let value = x.into_bigint().to_string();
+        line(&constant(&format!("{name}_{suffix}"), &value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tests for the verifier contract. `tests/fixtures/solidity` pins the contracts for
+    //! the keys here, and the EVM tests deploy the bytecode `generate.sh` compiles from
+    //! them with solc. They're ignored until that bytecode is committed; run them with
+    //! `cargo test -- --ignored evm`.
+    use super::*;
+    use crate::circuits::compare::{CompareCircuit, PrimeString};
+    use crate::circuits::groth16::{generate_proof_with_rng, setup_with_rng};
+    use crate::circuits::sum::SumCircuit;
+    use ark_groth16::ProvingKey;
+    use rand_chacha::rand_core::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use revm::db::{CacheDB, EmptyDB};
+    use revm::primitives::{Address, ExecutionResult, Output, TxKind, U256};
+    use revm::Evm;
+    use solang_parser::pt::{ContractPart, SourceUnitPart};
+    use std::path::PathBuf;
+
+    type TestEvm = Evm<'static, (), CacheDB<EmptyDB>>;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/solidity")
+            .join(name)
+    }
+
+    /// A compare circuit proving that "abc" is a prefix of "abcdef".
+    fn compare_circuit() -> CompareCircuit<Fr> {
+        CompareCircuit {
+            larger: Some(PrimeString::<Fr>::from("abcdef").into()),
+            shorter: Some(PrimeString::<Fr>::from("abc").into()),
+        }
+    }
+
+    /// The keys of the `sum` or `compare` fixture contract, and the rng after setup.
+    fn fixture_keys(name: &str) -> (ProvingKey<Bn254>, VerifyingKey<Bn254>, ChaCha20Rng) {
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (pk, vk) = match name {
+            "sum" => setup_with_rng(SumCircuit::default(), &mut rng),
+            "compare" => setup_with_rng(compare_circuit(), &mut rng),
+            _ => unreachable!("no fixture {name}"),
+        }
+        .expect("keys created");
+        (pk, vk, rng)
+    }
+
+    /// Deploys the bytecode of the `name` fixture contract, which is the verifier for `vk`.
+    fn deploy(name: &str, vk: &VerifyingKey<Bn254>) -> (TestEvm, Address) {
+        let source = std::fs::read_to_string(fixture(&format!("{name}.sol"))).expect("source");
+        assert_eq!(verifier_contract(vk).expect("contract written"), source);
+        let bin = std::fs::read_to_string(fixture(&format!("{name}.bin"))).expect("bytecode");
+        let bin = bin.trim();
+        let bytecode = (0..bin.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&bin[i..i + 2], 16).expect("hex bytecode"))
+            .collect::<Vec<_>>();
+        let mut evm = Evm::builder()
+            .with_db(CacheDB::new(EmptyDB::default()))
+            .modify_tx_env(|tx| {
+                tx.transact_to = TxKind::Create;
+                tx.data = bytecode.into();
+            })
+            .build();
+        match evm.transact_commit().expect("transaction executed") {
+            ExecutionResult::Success {
+                output: Output::Create(_, Some(address)),
+                ..
+            } => (evm, address),
+            result => panic!("deployment failed: {result:?}"),
+        }
+    }
+
+    /// Calls the verifier, returning its output or `None` if the call reverted.
+    fn call(evm: &mut TestEvm, address: Address, calldata: Vec<u8>) -> Option<Vec<u8>> {
+        let tx = evm.tx_mut();
+        tx.transact_to = TxKind::Call(address);
+        tx.data = calldata.into();
+        tx.value = U256::ZERO;
+        match evm.transact().expect("transaction executed").result {
+            ExecutionResult::Success {
+                output: Output::Call(output),
+                ..
+            } => Some(output.to_vec()),
+            _ => None,
+        }
+    }
+
+    fn verified(evm: &mut TestEvm, address: Address, calldata: Vec<u8>) -> bool {
+        let output = call(evm, address, calldata).expect("call succeeded");
+        assert_eq!(output.len(), 32);
+        assert!(output[..31].iter().all(|b| *b == 0));
+        output[31] == 1
+    }
+
+    /// Test a sum circuit proof on the EVM, along with tampered and malformed calls.
+    #[test]
+    #[ignore = "needs the bytecode written by tests/fixtures/solidity/generate.sh"]
+    fn evm_verifies_sum_proof() {
+        let (pk, vk, mut rng) = fixture_keys("sum");
+        let circuit = SumCircuit::new(Some(10.into()), Some(32.into()), Some(42.into()));
+        let proof = generate_proof_with_rng(&pk, circuit, &mut rng).expect("proof generated");
+        let (mut evm, address) = deploy("sum", &vk);
+
+        let calldata = encode_calldata(&proof, &[Fr::from(42u64)]);
+        assert_eq!(calldata.len(), 4 + 9 * 32);
+        assert!(verified(&mut evm, address, calldata.clone()));
+        assert!(!verified(
+            &mut evm,
+            address,
+            encode_calldata(&proof, &[Fr::from(43u64)])
+        ));
+
+        // The same input plus the modulus is rejected instead of reduced.
+        let mut unreduced = calldata.clone();
+        let input = Fr::from(42u64).into_bigint();
+        let mut sum = Fr::MODULUS;
+        sum.add_with_carry(&input);
+        unreduced[4 + 8 * 32..].copy_from_slice(&sum.to_bytes_be());
+        assert!(!verified(&mut evm, address, unreduced));
+
+        // A proof point that is not on the curve fails the pairing check.
+        let mut off_curve = calldata.clone();
+        off_curve[4 + 7 * 32 + 31] ^= 1;
+        assert!(!verified(&mut evm, address, off_curve));
+
+        // So does a proof for another statement.
+        let other = SumCircuit::new(Some(10.into()), Some(33.into()), Some(43.into()));
+        let other = generate_proof_with_rng(&pk, other, &mut rng).expect("proof generated");
+        let mut mixed = encode_calldata(&other, &[Fr::from(42u64)]);
+        mixed[4 + 2 * 32..4 + 6 * 32].copy_from_slice(&calldata[4 + 2 * 32..4 + 6 * 32]);
+        assert!(!verified(&mut evm, address, mixed));
+
+        assert!(call(&mut evm, address, calldata[..calldata.len() - 1].to_vec()).is_none());
+        let mut unknown = calldata;
+        unknown[0] ^= 1;
+        assert!(call(&mut evm, address, unknown).is_none());
+    }
+
+    /// Test a compare circuit proof, which has one public input per character.
+    #[test]
+    #[ignore = "needs the bytecode written by tests/fixtures/solidity/generate.sh"]
+    fn evm_verifies_compare_proof() {
+        let (pk, vk, mut rng) = fixture_keys("compare");
+        let shorter: Vec<Fr> = PrimeString::from("abc").into();
+        let proof =
+            generate_proof_with_rng(&pk, compare_circuit(), &mut rng).expect("proof generated");
+        let (mut evm, address) = deploy("compare", &vk);
+
+        assert!(verified(
+            &mut evm,
+            address,
+            encode_calldata(&proof, &shorter)
+        ));
+        let other: Vec<Fr> = PrimeString::from("abd").into();
+        assert!(!verified(
+            &mut evm,
+            address,
+            encode_calldata(&proof, &other)
+        ));
+        // A call for a different number of inputs has a different selector.
+        assert!(call(&mut evm, address, encode_calldata(&proof, &shorter[..2])).is_none());
+    }
+
+    /// Test that the contracts for the fixture keys are the ones pinned in
+    /// `tests/fixtures/solidity`, which the EVM tests deploy compiled. With
+    /// `UPDATE_FIXTURES` set, the pinned contracts are rewritten instead.
+    #[test]
+    fn contract_source_is_pinned() {
+        for name in ["sum", "compare"] {
+            let (_, vk, _) = fixture_keys(name);
+            let source = verifier_contract(&vk).expect("contract written");
+            let path = fixture(&format!("{name}.sol"));
+            if std::env::var_os("UPDATE_FIXTURES").is_some() {
+                std::fs::write(path, source).expect("source written");
+            } else {
+                let pinned = std::fs::read_to_string(path).expect("source read");
+                assert!(
+                    source == pinned,
+                    "the {name} contract changed; see generate.sh"
+                );
+            }
+        }
+    }
+
+    /// Test that the Solidity source parses and declares the function the calldata calls,
+    /// and that a key without input commitments is rejected.
+    #[test]
+    fn contract_is_valid_solidity() {
+        let (_, vk, _) = fixture_keys("sum");
+        let mut no_inputs = vk.clone();
+        no_inputs.gamma_abc_g1.truncate(1);
+        let mut malformed = vk.clone();
+        malformed.gamma_abc_g1.clear();
+        assert!(matches!(
+            verifier_contract(&malformed),
+            Err(Groth16Error::Synthesis(
+                SynthesisError::MalformedVerifyingKey
+            ))
+        ));
+
+        for (vk, inputs) in [(&vk, 1), (&no_inputs, 0)] {
+            let source = verifier_contract(vk).expect("contract written");
+            let (unit, _) = solang_parser::parse(&source, 0).expect("valid Solidity");
+            let functions = unit
+                .0
+                .iter()
+                .filter_map(|part| match part {
+                    SourceUnitPart::ContractDefinition(contract) => Some(&contract.parts),
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|part| match part {
+                    ContractPart::FunctionDefinition(f) => Some(f),
+                    _ => None,
+                })
+                .map(|f| (f.name.as_ref().expect("named").name.clone(), f.params.len()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                functions,
+                [
+                    ("verifyProof".to_string(), 3 + inputs.min(1)),
+                    ("ecAdd".to_string(), 2),
+                    ("ecMul".to_string(), 2)
+                ]
+            );
+            let x = vk.alpha_g1.x().expect("not infinity").into_bigint();
+            assert!(source.contains(&format!("uint256 constant ALPHA_X = {x};")));
+            assert_eq!(source.contains("calldata input"), inputs > 0);
+            let zero = Bn254Proof::from(ark_groth16::Proof {
+                a: G1Affine::zero(),
+                b: G2Affine::zero(),
+                c: G1Affine::zero(),
+            });
+            let calldata = encode_calldata(&zero, &vec![Fr::from(0u64); inputs]);
+            assert_eq!(calldata.len(), calldata_len(inputs));
+        }
+        // keccak256("verifyProof(uint256[2],uint256[2][2],uint256[2],uint256[1])")
+        assert_eq!(selector(1), [0x43, 0x75, 0x3b, 0x4d]);
+    }
+}
@@ -7,6 +7,8 @@
 //! `a + b = c` without revealing the values of `a` and `b`.
 //!
 
+use crate::circuits::container::CircuitId;
+use crate::circuits::groth16::Groth16Error;
 use ark_ff::PrimeField;
 use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
@@ -36,6 +38,13 @@ impl SumCircuit<ark_bn254::Fr> {
     }
 }
 
+impl<F: PrimeField> SumCircuit<F> {
+    /// Identifies the sum circuit in key and proof containers.
+    pub fn circuit_id() -> Result<CircuitId, Groth16Error> {
+        CircuitId::cached("sum", 0, Self::default)
+    }
+}
+
 impl<F: PrimeField> ConstraintSynthesizer<F> for SumCircuit<F> {
     /// Generates constraints for the sum circuit.
     ///
@@ -80,7 +89,8 @@ mod tests {
     //!
     //! These tests demonstrate how to create, prove, and verify a sum circuit.
     use super::*;
-    use crate::circuits::groth16::{generate_proof, setup, verify_proof};
+    use crate::circuits::groth16::{generate_proof, setup, verify_proof, Groth16Error};
+    use ark_bn254::Bn254;
 
     /// Test that we can prove and verify that 10 + 32 = 42.
     ///
@@ -91,7 +101,7 @@ mod tests {
     /// 4. Verifies the proof using the verification key and public input c
     #[test]
     fn prove_verify_sum() {
-        let (pk, vk) = setup(SumCircuit::default()).expect("keys created");
+        let (pk, vk) = setup::<Bn254, _>(SumCircuit::default()).expect("keys created");
 
         let proof = generate_proof(
             pk,
@@ -105,18 +115,50 @@ mod tests {
         assert!(verified, "this can't be verified");
     }
 
+    /// Proves and verifies that 10 + 32 = 42 over the scalar field of `E`.
+    #[cfg(any(feature = "bls12-381", feature = "bls12-377"))]
+    fn prove_verify_sum_on<E: ark_ec::pairing::Pairing>() {
+        let (pk, vk) = setup::<E, _>(SumCircuit::default()).expect("keys created");
+
+        let circuit = SumCircuit {
+            a: Some(10u64.into()),
+            b: Some(32u64.into()),
+            c: Some(42u64.into()),
+        };
+        let proof = generate_proof(pk, circuit).expect("proof created");
+
+        let verified = verify_proof(vk, &[42u64.into()], proof).expect("proof is verified");
+        assert!(verified, "this can't be verified");
+    }
+
+    #[cfg(feature = "bls12-381")]
+    #[test]
+    fn prove_verify_sum_bls12_381() {
+        prove_verify_sum_on::<crate::circuits::groth16::Bls12_381>();
+    }
+
+    #[cfg(feature = "bls12-377")]
+    #[test]
+    fn prove_verify_sum_bls12_377() {
+        prove_verify_sum_on::<crate::circuits::groth16::Bls12_377>();
+    }
+
     /// Test that proof generation fails when the sum constraint is not satisfied.
     ///
     /// This test demonstrates that the prover cannot generate a valid proof when
     /// the values don't satisfy the constraint (a + b = c). Here, 10 + 31 ≠ 42,
-    /// so the proof generation should fail.
+    /// so the proof generation should fail with an error.
     #[test]
-    #[should_panic(expected = "assertion failed: cs.is_satisfied().unwrap()")]
     fn prove_verify_bad_sum() {
-        let (pk, _) = setup(SumCircuit::default()).expect("keys created");
-        let _ = generate_proof(
+        let (pk, _) = setup::<Bn254, _>(SumCircuit::default()).expect("keys created");
+        let result = generate_proof(
             pk,
             SumCircuit::new(Some(10.into()), Some(31.into()), Some(42.into())),
         );
+
+        assert!(
+            matches!(result, Err(Groth16Error::Unsatisfied)),
+            "an unsatisfied circuit can't be proven"
+        );
     }
 }
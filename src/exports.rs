@@ -1,7 +1,259 @@
+use crate::circuits::compare::{CompareCircuit, PrimeString};
+use crate::circuits::container::{self, CircuitId};
+use crate::circuits::groth16::{Bn254Proof, Groth16Error, ProvingPhase, SerializationMode};
 use crate::circuits::sum::SumCircuit;
-use std::os::raw::{c_int, c_uchar};
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInt, PrimeField};
+use ark_groth16::{PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
+use ark_serialize::SerializationError;
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_uchar, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 use std::{mem, slice};
 
+/// Version of the C ABI exposed by this module.
+///
+/// It is incremented whenever an export or `#[repr(C)]` type changes incompatibly, so
+/// hosts can compare the value compiled into their header with the one reported by
+/// `trivial_circuits_abi_version` in the library they load.
+pub const TRIVIAL_CIRCUITS_ABI_VERSION: u32 = 2;
+
+/// Number of bytes in the little-endian encoding of a field element.
+pub const FIELD_ELEMENT_LENGTH: usize = 32;
+
+/// Error codes reported across the C ABI.
+///
+/// The code of the most recent failure on the calling thread is available from
+/// `last_error_code`, and a description of it from `last_error_message`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// The proving or verifying key bytes could not be deserialized or are malformed.
+    InvalidKey = 2,
+    /// The proof bytes could not be deserialized.
+    InvalidProof = 3,
+    /// The private inputs do not satisfy the circuit.
+    InvalidWitness = 4,
+    /// An unexpected failure, such as a serialization error.
+    Internal = 5,
+    /// The library panicked; the panic was caught before it reached the caller.
+    Panic = 6,
+    /// An input is not a canonical encoding of a field element.
+    InvalidInput = 7,
+    /// The proving job was cancelled with `cancel_proving_job`.
+    Cancelled = 8,
+    /// The number of public inputs does not match the verifying key.
+    PublicInputMismatch = 9,
+}
+
+/// An error raised by an export, recorded as the thread's last error.
+struct FfiError {
+    code: ErrorCode,
+    message: String,
+}
+
+impl FfiError {
+    fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl ErrorCode {
+    /// The name of the code, used as the error name by the language bindings.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::Ok => "Ok",
+            ErrorCode::NullPointer => "NullPointer",
+            ErrorCode::InvalidKey => "InvalidKey",
+            ErrorCode::InvalidProof => "InvalidProof",
+            ErrorCode::InvalidWitness => "InvalidWitness",
+            ErrorCode::Internal => "Internal",
+            ErrorCode::Panic => "Panic",
+            ErrorCode::InvalidInput => "InvalidInput",
+            ErrorCode::Cancelled => "Cancelled",
+            ErrorCode::PublicInputMismatch => "PublicInputMismatch",
+        }
+    }
+}
+
+impl From<&Groth16Error> for ErrorCode {
+    /// Picks the code for an error from `circuits::groth16`. Deserialization errors are
+    /// reported as `InvalidInput`; the key and proof loaders use more specific codes.
+    fn from(e: &Groth16Error) -> Self {
+        match e {
+            Groth16Error::Serialization(_) => ErrorCode::Internal,
+            Groth16Error::Deserialization(_) => ErrorCode::InvalidInput,
+            Groth16Error::Synthesis(SynthesisError::MalformedVerifyingKey) => ErrorCode::InvalidKey,
+            Groth16Error::Synthesis(_) | Groth16Error::Unsatisfied => ErrorCode::InvalidWitness,
+            Groth16Error::PublicInputMismatch { .. } => ErrorCode::PublicInputMismatch,
+            Groth16Error::Cancelled => ErrorCode::Cancelled,
+            Groth16Error::NoProofs
+            | Groth16Error::TooManyProofs { .. }
+            | Groth16Error::InvalidAggregationSize
+            | Groth16Error::InvalidAggregationKey
+            | Groth16Error::InvalidContribution { .. }
+            | Groth16Error::CircuitMismatch
+            | Groth16Error::CeremonyPhase
+            | Groth16Error::CircuitTooLarge { .. }
+            | Groth16Error::InvalidPowersOfTau
+            | Groth16Error::InvalidContainer
+            | Groth16Error::ArtifactMismatch { .. } => ErrorCode::InvalidInput,
+        }
+    }
+}
+
+impl From<Groth16Error> for FfiError {
+    fn from(e: Groth16Error) -> Self {
+        FfiError::new(ErrorCode::from(&e), e.to_string())
+    }
+}
+
+/// A proving key loaded once with `load_proving_key` and reused for many proofs.
+///
+/// The handle is opaque to C callers and must be released with `free_proving_key`. The
+/// key is shared with any proving job started from it, so the handle may be freed while
+/// jobs are still running.
+pub struct ProvingKeyHandle {
+    key: Arc<ProvingKey<Bn254>>,
+    circuit: CircuitId,
+}
+
+/// A verifying key loaded once with `load_verifying_key` and reused for many
+/// verifications.
+///
+/// The key is stored in its prepared form. The handle is opaque to C callers and must
+/// be released with `free_verifying_key`.
+pub struct VerifyingKeyHandle {
+    key: PreparedVerifyingKey<Bn254>,
+    circuit: CircuitId,
+}
+
+/// A proof being generated on a background thread, started by one of the `_async`
+/// exports.
+///
+/// The handle is opaque to C callers and must be released with `free_proving_job`.
+pub struct ProvingJob {
+    cancelled: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// Called by a proving job as each phase of the prover starts. May be null.
+pub type ProgressCallback = Option<extern "C" fn(user_data: *mut c_void, phase: ProvingPhase)>;
+
+/// Called exactly once when a proving job finishes.
+///
+/// On success `code` is `ErrorCode::Ok` and `proof` holds the serialized proof, which the
+/// callback takes ownership of and must release with `free_byte_buffer`. Otherwise
+/// `proof` is a null buffer and `message` describes the failure; the message is only
+/// valid for the duration of the callback.
+pub type CompletionCallback = Option<
+    extern "C" fn(
+        user_data: *mut c_void,
+        code: ErrorCode,
+        proof: ByteBuffer,
+        message: *const c_char,
+    ),
+>;
+
+/// The caller's context pointer, handed back to the callbacks of a proving job.
+struct UserData(*mut c_void);
+
+// The pointer is never dereferenced here; the caller guarantees that the callbacks may
+// use it from the worker thread.
+unsafe impl Send for UserData {}
+
+impl UserData {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// An array of bytes owned by the caller.
+///
+/// Buffers returned by the exports must be released with `free_byte_buffer`, which
+/// uses the recorded `capacity` to rebuild the original allocation. A failed call
+/// returns a buffer with a null `data` pointer.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ByteBuffer {
+    /// Pointer to the first byte, or null if there is no buffer.
+    pub data: *mut c_uchar,
+    /// Number of bytes in the buffer.
+    pub len: usize,
+    /// Capacity of the allocation backing the buffer.
+    pub capacity: usize,
+}
+
+impl ByteBuffer {
+    /// A buffer that owns no memory, returned when a call fails.
+    fn null() -> Self {
+        Self {
+            data: std::ptr::null_mut(),
+            len: 0,
+            capacity: 0,
+        }
+    }
+}
+
+impl From<Vec<u8>> for ByteBuffer {
+    /// Hands ownership of `bytes` to the caller.
+    fn from(bytes: Vec<u8>) -> Self {
+        let mut bytes = mem::ManuallyDrop::new(bytes);
+        Self {
+            data: bytes.as_mut_ptr(),
+            len: bytes.len(),
+            capacity: bytes.capacity(),
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(ErrorCode, CString)>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, turning a panic into an `ErrorCode::Panic` error so that it never unwinds
+/// across the C ABI.
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, FfiError>) -> Result<T, FfiError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(FfiError::new(ErrorCode::Panic, message))
+    })
+}
+
+/// Runs the body of an export, catching any panic so that it never unwinds across
+/// the C ABI, and records the outcome as the thread's last error.
+fn record_result<T>(f: impl FnOnce() -> Result<T, FfiError>) -> Option<T> {
+    let result = catch_panic(f);
+
+    LAST_ERROR.with(|last| match result {
+        Ok(value) => {
+            *last.borrow_mut() = None;
+            Some(value)
+        }
+        Err(e) => {
+            let message = CString::new(e.message).unwrap_or_default();
+            *last.borrow_mut() = Some((e.code, message));
+            None
+        }
+    })
+}
+
 fn convert_to_vec(ptr: *const c_uchar, length: usize) -> Vec<u8> {
     if !ptr.is_null() {
         unsafe {
@@ -12,17 +264,361 @@ fn convert_to_vec(ptr: *const c_uchar, length: usize) -> Vec<u8> {
     Vec::new()
 }
 
+/// Reads a field element from `FIELD_ELEMENT_LENGTH` little-endian bytes, the encoding
+/// taken by `generate_proof_for_sum_bytes`.
+///
+/// Fails with a deserialization error if `bytes` has another length or encodes a value
+/// that is not smaller than the field modulus.
+pub fn field_element_from_bytes(bytes: &[u8]) -> Result<Fr, Groth16Error> {
+    let bytes: &[u8; FIELD_ELEMENT_LENGTH] = bytes
+        .try_into()
+        .map_err(|_| Groth16Error::Deserialization(SerializationError::InvalidData))?;
+    let limbs = std::array::from_fn(|i| {
+        u64::from_le_bytes(bytes[i * 8..(i + 1) * 8].try_into().expect("8 bytes"))
+    });
+    Fr::from_bigint(BigInt::new(limbs)).ok_or(Groth16Error::Deserialization(
+        SerializationError::InvalidData,
+    ))
+}
+
+/// Reads a field element from a pointer to `FIELD_ELEMENT_LENGTH` bytes.
+unsafe fn field_element_from_ptr(ptr: *const c_uchar) -> Result<Fr, FfiError> {
+    if ptr.is_null() {
+        return Err(FfiError::new(
+            ErrorCode::NullPointer,
+            "field element pointer must not be null",
+        ));
+    }
+
+    field_element_from_bytes(slice::from_raw_parts(ptr, FIELD_ELEMENT_LENGTH)).map_err(|_| {
+        FfiError::new(
+            ErrorCode::InvalidInput,
+            "value is not a canonical field element",
+        )
+    })
+}
+
+fn sum_circuit() -> Result<CircuitId, FfiError> {
+    Ok(SumCircuit::<Fr>::circuit_id()?)
+}
+
+fn compare_circuit(prefix_length: usize) -> Result<CircuitId, FfiError> {
+    Ok(CompareCircuit::<Fr>::circuit_id(prefix_length)?)
+}
+
+fn proving_key_from_bytes(
+    bytes: Vec<u8>,
+    circuit: &CircuitId,
+) -> Result<ProvingKey<Bn254>, FfiError> {
+    container::from_bytes(&bytes, circuit)
+        .map_err(|e| FfiError::new(ErrorCode::InvalidKey, e.to_string()))
+}
+
+fn verifying_key_from_bytes(
+    bytes: Vec<u8>,
+    circuit: &CircuitId,
+) -> Result<VerifyingKey<Bn254>, FfiError> {
+    container::from_bytes(&bytes, circuit)
+        .map_err(|e| FfiError::new(ErrorCode::InvalidKey, e.to_string()))
+}
+
+fn proof_from_bytes(bytes: Vec<u8>, circuit: &CircuitId) -> Result<Bn254Proof, FfiError> {
+    container::from_bytes::<Proof<Bn254>>(&bytes, circuit)
+        .map(Bn254Proof::from)
+        .map_err(|e| FfiError::new(ErrorCode::InvalidProof, e.to_string()))
+}
+
+/// Fails with `ErrorCode::InvalidKey` if a loaded key was made for another circuit.
+fn check_key_circuit(key: &CircuitId, expected: &CircuitId) -> Result<(), FfiError> {
+    key.check(expected)
+        .map_err(|e| FfiError::new(ErrorCode::InvalidKey, e.to_string()))
+}
+
+/// Serializes a proof of `circuit` into a container.
+fn proof_to_bytes(proof: &Bn254Proof, circuit: &CircuitId) -> Result<Vec<u8>, FfiError> {
+    Ok(container::to_bytes(
+        proof.as_ref(),
+        circuit,
+        SerializationMode::UNCOMPRESSED,
+    )?)
+}
+
+/// Proves `circuit` with the serialized proving key `pk`, returning the serialized proof.
+fn prove<C>(pk: Vec<u8>, circuit: C, id: &CircuitId) -> Result<Vec<u8>, FfiError>
+where
+    C: ConstraintSynthesizer<Fr>,
+{
+    let pk = proving_key_from_bytes(pk, id)?;
+    let proof = crate::circuits::groth16::generate_proof_with_key(&pk, circuit)?;
+    proof_to_bytes(&proof, id)
+}
+
+/// Proves `circuit` with a loaded proving key, returning the serialized proof.
+fn prove_with_key<C>(pk: &ProvingKeyHandle, circuit: C, id: &CircuitId) -> Result<Vec<u8>, FfiError>
+where
+    C: ConstraintSynthesizer<Fr>,
+{
+    check_key_circuit(&pk.circuit, id)?;
+    let proof = crate::circuits::groth16::generate_proof_with_key(&pk.key, circuit)?;
+    proof_to_bytes(&proof, id)
+}
+
+/// Proves `circuit` on a new thread, reporting progress and the outcome through the
+/// caller's callbacks.
+fn spawn_proving_job<C>(
+    pk: &ProvingKeyHandle,
+    circuit: C,
+    id: CircuitId,
+    on_progress: ProgressCallback,
+    on_complete: CompletionCallback,
+    user_data: *mut c_void,
+) -> Result<ProvingJob, FfiError>
+where
+    C: ConstraintSynthesizer<Fr> + Send + 'static,
+{
+    let on_complete = on_complete.ok_or_else(|| {
+        FfiError::new(
+            ErrorCode::NullPointer,
+            "completion callback must not be null",
+        )
+    })?;
+    check_key_circuit(&pk.circuit, &id)?;
+    let pk = Arc::clone(&pk.key);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&cancelled);
+    let user_data = UserData(user_data);
+
+    let worker = thread::Builder::new()
+        .name("trivial-circuits-prover".to_string())
+        .spawn(move || {
+            let result = catch_panic(|| {
+                let proof = crate::circuits::groth16::generate_proof_with_progress(
+                    &pk,
+                    circuit,
+                    |phase| {
+                        if flag.load(Ordering::Acquire) {
+                            return false;
+                        }
+                        if let Some(on_progress) = on_progress {
+                            on_progress(user_data.get(), phase);
+                        }
+                        true
+                    },
+                )?;
+                proof_to_bytes(&proof, &id)
+            });
+
+            match result {
+                Ok(proof) => on_complete(
+                    user_data.get(),
+                    ErrorCode::Ok,
+                    proof.into(),
+                    std::ptr::null(),
+                ),
+                Err(e) => {
+                    let message = CString::new(e.message).unwrap_or_default();
+                    on_complete(
+                        user_data.get(),
+                        e.code,
+                        ByteBuffer::null(),
+                        message.as_ptr(),
+                    );
+                }
+            }
+        })
+        .map_err(|e| FfiError::new(ErrorCode::Internal, e.to_string()))?;
+
+    Ok(ProvingJob {
+        cancelled,
+        worker: Some(worker),
+    })
+}
+
+/// Starts a proving job, returning its handle or null on failure.
+fn proving_job_handle(f: impl FnOnce() -> Result<ProvingJob, FfiError>) -> *mut ProvingJob {
+    match record_result(f) {
+        Some(job) => Box::into_raw(Box::new(job)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Verifies the serialized `proof` against the serialized verifying key `vk`.
+fn verify(
+    vk: Vec<u8>,
+    proof: Vec<u8>,
+    public_input: &[Fr],
+    id: &CircuitId,
+) -> Result<bool, FfiError> {
+    let vk = verifying_key_from_bytes(vk, id)?;
+    let proof = proof_from_bytes(proof, id)?;
+    Ok(crate::circuits::groth16::verify_proof(
+        vk,
+        public_input,
+        proof,
+    )?)
+}
+
+/// Verifies the serialized `proof` against a loaded and prepared verifying key.
+fn verify_with_key(
+    vk: &VerifyingKeyHandle,
+    proof: Vec<u8>,
+    public_input: &[Fr],
+    id: &CircuitId,
+) -> Result<bool, FfiError> {
+    check_key_circuit(&vk.circuit, id)?;
+    let proof = proof_from_bytes(proof, id)?;
+    Ok(crate::circuits::groth16::verify_proof_with_prepared_key(
+        &vk.key,
+        public_input,
+        &proof,
+    )?)
+}
+
+/// Borrows the key behind a handle, failing if the handle is null.
+unsafe fn handle_ref<'a, T>(handle: *const T) -> Result<&'a T, FfiError> {
+    handle
+        .as_ref()
+        .ok_or_else(|| FfiError::new(ErrorCode::NullPointer, "key handle must not be null"))
+}
+
+/// Converts the outcome of a verification to the tri-state result of the exports.
+fn verification_status(f: impl FnOnce() -> Result<bool, FfiError>) -> c_int {
+    match record_result(f) {
+        Some(verified) => verified as c_int,
+        None => -1,
+    }
+}
+
+/// Runs the setup for `circuit` and hands the serialized keys to the caller.
+unsafe fn setup_keys<C>(
+    circuit: C,
+    id: impl FnOnce() -> Result<CircuitId, FfiError>,
+    pk_out: *mut ByteBuffer,
+    vk_out: *mut ByteBuffer,
+) -> ErrorCode
+where
+    C: ConstraintSynthesizer<Fr>,
+{
+    let keys = || {
+        if pk_out.is_null() || vk_out.is_null() {
+            return Err(FfiError::new(
+                ErrorCode::NullPointer,
+                "key output pointers must not be null",
+            ));
+        }
+
+        let id = id()?;
+        let (pk, vk) = crate::circuits::groth16::setup::<Bn254, _>(circuit)
+            .map_err(|e| FfiError::new(ErrorCode::Internal, e.to_string()))?;
+        let mode = SerializationMode::UNCOMPRESSED;
+        Ok((
+            container::to_bytes(&pk, &id, mode)?,
+            container::to_bytes(&vk, &id, mode)?,
+        ))
+    };
+
+    match record_result(keys) {
+        Some((pk, vk)) => {
+            *pk_out = pk.into();
+            *vk_out = vk.into();
+            ErrorCode::Ok
+        }
+        None => last_error_code(),
+    }
+}
+
+#[no_mangle]
+/// Returns the version of the C ABI implemented by this library.
+///
+/// Hosts should check it against `TRIVIAL_CIRCUITS_ABI_VERSION` from the header they
+/// were compiled with before calling any other export.
+pub extern "C" fn trivial_circuits_abi_version() -> u32 {
+    TRIVIAL_CIRCUITS_ABI_VERSION
+}
+
+#[no_mangle]
+/// Returns the code of the most recent failure on the calling thread.
+///
+/// Every export resets the last error when it succeeds, so this returns `Ok` if the
+/// previous call on this thread did not fail.
+pub extern "C" fn last_error_code() -> ErrorCode {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ErrorCode::Ok, |(code, _)| *code)
+    })
+}
+
+#[no_mangle]
+/// Returns a description of the most recent failure on the calling thread.
+///
+/// Returns null if the previous call on this thread did not fail. The returned string
+/// is owned by the library and remains valid until the next call to an export on the
+/// same thread; it must not be freed by the caller.
+pub extern "C" fn last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |(_, message)| message.as_ptr())
+    })
+}
+
+#[no_mangle]
+/// Generates the proving and verifying keys for the sum circuit.
+///
+/// The keys are written in the format of `circuits::container`, and the other exports
+/// reject keys and proofs that were made for another circuit with `ErrorCode::InvalidKey`
+/// and `ErrorCode::InvalidProof`. Returns `ErrorCode::Ok` on success, or the reason the keys could not be generated.
+///
+/// # Safety
+///
+/// - `pk_out` and `vk_out` must be valid pointers to locations where the buffers holding
+///   the serialized proving and verifying keys will be stored.
+/// - Each key must be released with `free_byte_buffer`.
+pub unsafe extern "C" fn setup_for_sum(
+    pk_out: *mut ByteBuffer,
+    vk_out: *mut ByteBuffer,
+) -> ErrorCode {
+    setup_keys(SumCircuit::<Fr>::default(), sum_circuit, pk_out, vk_out)
+}
+
+#[no_mangle]
+/// Generates the proving and verifying keys for the compare circuit.
+///
+/// The keys are specific to prefixes of exactly `prefix_length` bytes, and are written
+/// in the format of `circuits::container`. Returns `ErrorCode::Ok` on success, or the reason the keys could not be generated.
+///
+/// # Safety
+///
+/// - `pk_out` and `vk_out` must be valid pointers to locations where the buffers holding
+///   the serialized proving and verifying keys will be stored.
+/// - Each key must be released with `free_byte_buffer`.
+pub unsafe extern "C" fn setup_for_compare(
+    prefix_length: usize,
+    pk_out: *mut ByteBuffer,
+    vk_out: *mut ByteBuffer,
+) -> ErrorCode {
+    setup_keys(
+        CompareCircuit::<Fr>::with_prefix_length(prefix_length),
+        || compare_circuit(prefix_length),
+        pk_out,
+        vk_out,
+    )
+}
+
 #[no_mangle]
 /// Generates a cryptographic proof for a sum operation.
 ///
+/// Returns a null buffer on failure; the reason is available from `last_error_code` and
+/// `last_error_message`.
+///
 /// # Safety
 ///
 /// - `pk` must be a valid pointer to an array of bytes that represents the public key,
 ///   with `pk_length` specifying the number of bytes in the array.
-/// - `out_len` must be a valid pointer to a memory location where the length of the
-///   output will be stored. It should not be null unless you intend not to store the length.
-/// - The caller must ensure that memory management of the returned pointer is properly handled
-///   to avoid leaks or invalid access, using `free_bytes` when the memory is no longer needed.
+/// - The caller must ensure that memory management of the returned buffer is properly handled
+///   to avoid leaks or invalid access, using `free_byte_buffer` when the memory is no longer
+///   needed.
 /// - The function is unsafe due to dereferencing raw pointers and should be called
 ///   within an `unsafe` block in Rust.
 pub unsafe extern "C" fn generate_proof_for_sum(
@@ -31,84 +627,1094 @@ pub unsafe extern "C" fn generate_proof_for_sum(
     a: u32,
     b: u32,
     c: u32,
-    out_len: *mut c_int,
-) -> *mut c_uchar {
-    let pk = convert_to_vec(pk, pk_length);
-    if let Ok(pk) = crate::circuits::groth16::from_bytes(pk) {
-        if let Ok(proof) = crate::circuits::groth16::generate_proof(
+) -> ByteBuffer {
+    let proof = || {
+        let pk = convert_to_vec(pk, pk_length);
+        prove(
             pk,
             SumCircuit::new(Some(a.into()), Some(b.into()), Some(c.into())),
-        ) {
-            if let Ok(mut proof_bytes) =
-                crate::circuits::groth16::TrySerializer::try_to_bytes(proof.as_ref())
-            {
-                if !out_len.is_null() {
-                    *out_len = proof_bytes.len() as c_int;
-                }
+            &sum_circuit()?,
+        )
+    };
 
-                let ptr = proof_bytes.as_mut_ptr();
+    match record_result(proof) {
+        Some(proof_bytes) => proof_bytes.into(),
+        None => ByteBuffer::null(),
+    }
+}
 
-                mem::forget(proof_bytes);
+#[no_mangle]
+/// Verifies a cryptographic proof for a sum operation.
+///
+/// Returns `1` if the proof is valid for the public value `c`, `0` if it is not,
+/// and `-1` if the verifying key or proof could not be deserialized or verification
+/// failed with an error; the reason is available from `last_error_code` and
+/// `last_error_message`.
+///
+/// # Safety
+///
+/// - `vk` must be a valid pointer to an array of bytes that represents the verifying key,
+///   with `vk_length` specifying the number of bytes in the array.
+/// - `proof` must be a valid pointer to an array of bytes that represents the proof,
+///   with `proof_length` specifying the number of bytes in the array.
+/// - The function is unsafe due to dereferencing raw pointers and should be called
+///   within an `unsafe` block in Rust.
+pub unsafe extern "C" fn verify_proof_for_sum(
+    vk: *const c_uchar,
+    vk_length: usize,
+    proof: *const c_uchar,
+    proof_length: usize,
+    c: u32,
+) -> c_int {
+    verification_status(|| {
+        let vk = convert_to_vec(vk, vk_length);
+        let proof = convert_to_vec(proof, proof_length);
+        verify(vk, proof, &[c.into()], &sum_circuit()?)
+    })
+}
 
-                return ptr as *mut c_uchar;
-            }
-        }
+#[no_mangle]
+/// Generates a cryptographic proof for a sum operation over 64-bit values.
+///
+/// Returns a null buffer on failure; the reason is available from `last_error_code` and
+/// `last_error_message`.
+///
+/// # Safety
+///
+/// - `pk` must be a valid pointer to an array of bytes that represents the proving key,
+///   with `pk_length` specifying the number of bytes in the array.
+/// - The returned buffer must be released with `free_byte_buffer`.
+pub unsafe extern "C" fn generate_proof_for_sum_u64(
+    pk: *const c_uchar,
+    pk_length: usize,
+    a: u64,
+    b: u64,
+    c: u64,
+) -> ByteBuffer {
+    let proof = || {
+        let pk = convert_to_vec(pk, pk_length);
+        prove(
+            pk,
+            SumCircuit::new(Some(a.into()), Some(b.into()), Some(c.into())),
+            &sum_circuit()?,
+        )
+    };
+
+    match record_result(proof) {
+        Some(proof_bytes) => proof_bytes.into(),
+        None => ByteBuffer::null(),
     }
+}
 
-    std::ptr::null_mut()
+#[no_mangle]
+/// Verifies a cryptographic proof for a sum operation over 64-bit values.
+///
+/// Returns `1` if the proof is valid for the public value `c`, `0` if it is not,
+/// and `-1` on error; the reason is available from `last_error_code` and
+/// `last_error_message`.
+///
+/// # Safety
+///
+/// - `vk` must be a valid pointer to an array of bytes that represents the verifying key,
+///   with `vk_length` specifying the number of bytes in the array.
+/// - `proof` must be a valid pointer to an array of bytes that represents the proof,
+///   with `proof_length` specifying the number of bytes in the array.
+pub unsafe extern "C" fn verify_proof_for_sum_u64(
+    vk: *const c_uchar,
+    vk_length: usize,
+    proof: *const c_uchar,
+    proof_length: usize,
+    c: u64,
+) -> c_int {
+    verification_status(|| {
+        let vk = convert_to_vec(vk, vk_length);
+        let proof = convert_to_vec(proof, proof_length);
+        verify(vk, proof, &[c.into()], &sum_circuit()?)
+    })
 }
 
 #[no_mangle]
-/// Frees a previously allocated array of bytes.
+/// Generates a cryptographic proof for a sum operation over arbitrary field elements.
+///
+/// `a`, `b` and `c` are each `FIELD_ELEMENT_LENGTH` bytes holding a little-endian
+/// field element. Values that are not smaller than the field modulus are rejected
+/// with `ErrorCode::InvalidInput` rather than reduced. Returns a null buffer on
+/// failure; the reason is available from `last_error_code` and `last_error_message`.
 ///
 /// # Safety
 ///
-/// The pointer `ptr` must be a valid pointer to memory that was previously
-/// allocated by a `Vec<c_uchar>` with exactly `len` elements and `capacity`
-/// equal to the original vector's capacity. This function should only be called
-/// once for any given allocation to avoid double-free errors. `ptr` must not
-/// be null unless `len` and `capacity` are both zero.
-pub unsafe extern "C" fn free_bytes(ptr: *mut c_uchar, len: c_int, capacity: c_int) {
-    if !ptr.is_null() {
-        unsafe {
+/// - `pk` must be a valid pointer to an array of bytes that represents the proving key,
+///   with `pk_length` specifying the number of bytes in the array.
+/// - `a`, `b` and `c` must be valid pointers to arrays of `FIELD_ELEMENT_LENGTH` bytes.
+/// - The returned buffer must be released with `free_byte_buffer`.
+pub unsafe extern "C" fn generate_proof_for_sum_bytes(
+    pk: *const c_uchar,
+    pk_length: usize,
+    a: *const c_uchar,
+    b: *const c_uchar,
+    c: *const c_uchar,
+) -> ByteBuffer {
+    let proof = || {
+        let circuit = SumCircuit::new(
+            Some(field_element_from_ptr(a)?),
+            Some(field_element_from_ptr(b)?),
+            Some(field_element_from_ptr(c)?),
+        );
+        let pk = convert_to_vec(pk, pk_length);
+        prove(pk, circuit, &sum_circuit()?)
+    };
+
+    match record_result(proof) {
+        Some(proof_bytes) => proof_bytes.into(),
+        None => ByteBuffer::null(),
+    }
+}
+
+#[no_mangle]
+/// Verifies a cryptographic proof for a sum operation over arbitrary field elements.
+///
+/// `c` is `FIELD_ELEMENT_LENGTH` bytes holding a little-endian field element, and is
+/// rejected with `ErrorCode::InvalidInput` if it is not smaller than the field modulus.
+/// Returns `1` if the proof is valid for `c`, `0` if it is not, and `-1` on error; the
+/// reason is available from `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// - `vk` must be a valid pointer to an array of bytes that represents the verifying key,
+///   with `vk_length` specifying the number of bytes in the array.
+/// - `proof` must be a valid pointer to an array of bytes that represents the proof,
+///   with `proof_length` specifying the number of bytes in the array.
+/// - `c` must be a valid pointer to an array of `FIELD_ELEMENT_LENGTH` bytes.
+pub unsafe extern "C" fn verify_proof_for_sum_bytes(
+    vk: *const c_uchar,
+    vk_length: usize,
+    proof: *const c_uchar,
+    proof_length: usize,
+    c: *const c_uchar,
+) -> c_int {
+    verification_status(|| {
+        let c = field_element_from_ptr(c)?;
+        let vk = convert_to_vec(vk, vk_length);
+        let proof = convert_to_vec(proof, proof_length);
+        verify(vk, proof, &[c], &sum_circuit()?)
+    })
+}
+
+#[no_mangle]
+/// Generates a cryptographic proof that a private string starts with a public prefix.
+///
+/// Each byte of `prefix` and `full` is converted to a field element, as `PrimeString`
+/// does for strings. Returns a null buffer on failure; the reason is available from
+/// `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// - `pk` must be a valid pointer to an array of bytes that represents the proving key,
+///   with `pk_length` specifying the number of bytes in the array.
+/// - `prefix` and `full` must be valid pointers to arrays of `prefix_length` and
+///   `full_length` bytes respectively.
+/// - The caller must ensure that memory management of the returned buffer is properly handled
+///   to avoid leaks or invalid access, using `free_byte_buffer` when the memory is no longer
+///   needed.
+pub unsafe extern "C" fn generate_proof_for_compare(
+    pk: *const c_uchar,
+    pk_length: usize,
+    prefix: *const c_uchar,
+    prefix_length: usize,
+    full: *const c_uchar,
+    full_length: usize,
+) -> ByteBuffer {
+    let proof = || {
+        let pk = convert_to_vec(pk, pk_length);
+        let prefix = PrimeString::<Fr>::from(convert_to_vec(prefix, prefix_length).as_slice());
+        let full = PrimeString::<Fr>::from(convert_to_vec(full, full_length).as_slice());
+        prove(
+            pk,
+            CompareCircuit {
+                shorter: Some(prefix.into()),
+                larger: Some(full.into()),
+            },
+            &compare_circuit(prefix_length)?,
+        )
+    };
+
+    match record_result(proof) {
+        Some(proof_bytes) => proof_bytes.into(),
+        None => ByteBuffer::null(),
+    }
+}
+
+#[no_mangle]
+/// Verifies a cryptographic proof that a private string starts with a public prefix.
+///
+/// Returns `1` if the proof is valid for `prefix`, `0` if it is not, and `-1` if the
+/// verifying key or proof could not be deserialized or verification failed with an
+/// error; the reason is available from `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// - `vk` must be a valid pointer to an array of bytes that represents the verifying key,
+///   with `vk_length` specifying the number of bytes in the array.
+/// - `proof` must be a valid pointer to an array of bytes that represents the proof,
+///   with `proof_length` specifying the number of bytes in the array.
+/// - `prefix` must be a valid pointer to an array of `prefix_length` bytes.
+pub unsafe extern "C" fn verify_proof_for_compare(
+    vk: *const c_uchar,
+    vk_length: usize,
+    proof: *const c_uchar,
+    proof_length: usize,
+    prefix: *const c_uchar,
+    prefix_length: usize,
+) -> c_int {
+    verification_status(|| {
+        let vk = convert_to_vec(vk, vk_length);
+        let proof = convert_to_vec(proof, proof_length);
+        let prefix = PrimeString::<Fr>::from(convert_to_vec(prefix, prefix_length).as_slice());
+        verify(
+            vk,
+            proof,
+            &Vec::<Fr>::from(prefix),
+            &compare_circuit(prefix_length)?,
+        )
+    })
+}
+
+#[no_mangle]
+/// Loads a serialized proving key into a handle that can be used for many proofs.
+///
+/// The key may be for any circuit; the exports that prove with the handle fail with
+/// `ErrorCode::InvalidKey` if it is not for theirs.
+/// Returns null on failure; the reason is available from `last_error_code` and
+/// `last_error_message`.
+///
+/// # Safety
+///
+/// - `pk` must be a valid pointer to an array of bytes that represents the proving key,
+///   with `pk_length` specifying the number of bytes in the array.
+/// - The returned handle must be released with `free_proving_key`.
+pub unsafe extern "C" fn load_proving_key(
+    pk: *const c_uchar,
+    pk_length: usize,
+) -> *mut ProvingKeyHandle {
+    let handle = || {
+        let pk = convert_to_vec(pk, pk_length);
+        let (circuit, pk) = container::open::<ProvingKey<Bn254>>(&pk)
+            .map_err(|e| FfiError::new(ErrorCode::InvalidKey, e.to_string()))?;
+        Ok(ProvingKeyHandle {
+            key: Arc::new(pk),
+            circuit,
+        })
+    };
+
+    match record_result(handle) {
+        Some(handle) => Box::into_raw(Box::new(handle)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+/// Frees a proving key handle returned by `load_proving_key`.
+///
+/// # Safety
+///
+/// `handle` must have been returned by `load_proving_key` and must not be used after
+/// this call. Null handles are ignored.
+pub unsafe extern "C" fn free_proving_key(handle: *mut ProvingKeyHandle) {
+    if !handle.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(handle))));
+    }
+}
+
+#[no_mangle]
+/// Loads a serialized verifying key into a handle that can be used for many
+/// verifications.
+///
+/// The key is prepared once while loading. Returns null on failure; the reason is
+/// available from `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// - `vk` must be a valid pointer to an array of bytes that represents the verifying key,
+///   with `vk_length` specifying the number of bytes in the array.
+/// - The returned handle must be released with `free_verifying_key`.
+pub unsafe extern "C" fn load_verifying_key(
+    vk: *const c_uchar,
+    vk_length: usize,
+) -> *mut VerifyingKeyHandle {
+    let handle = || {
+        let vk = convert_to_vec(vk, vk_length);
+        let (circuit, vk) = container::open::<VerifyingKey<Bn254>>(&vk)
+            .map_err(|e| FfiError::new(ErrorCode::InvalidKey, e.to_string()))?;
+        Ok(VerifyingKeyHandle {
+            key: crate::circuits::groth16::prepare_verifying_key(&vk),
+            circuit,
+        })
+    };
+
+    match record_result(handle) {
+        Some(handle) => Box::into_raw(Box::new(handle)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+/// Frees a verifying key handle returned by `load_verifying_key`.
+///
+/// # Safety
+///
+/// `handle` must have been returned by `load_verifying_key` and must not be used after
+/// this call. Null handles are ignored.
+pub unsafe extern "C" fn free_verifying_key(handle: *mut VerifyingKeyHandle) {
+    if !handle.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(handle))));
+    }
+}
+
+#[no_mangle]
+/// Generates a cryptographic proof for a sum operation with a loaded proving key.
+///
+/// Returns a null buffer on failure; the reason is available from `last_error_code`
+/// and `last_error_message`.
+///
+/// # Safety
+///
+/// - `pk` must be a handle returned by `load_proving_key` that has not been freed.
+/// - The returned buffer must be released with `free_byte_buffer`.
+pub unsafe extern "C" fn generate_proof_for_sum_with_key(
+    pk: *const ProvingKeyHandle,
+    a: u32,
+    b: u32,
+    c: u32,
+) -> ByteBuffer {
+    let proof = || {
+        let pk = handle_ref(pk)?;
+        prove_with_key(
+            pk,
+            SumCircuit::new(Some(a.into()), Some(b.into()), Some(c.into())),
+            &sum_circuit()?,
+        )
+    };
+
+    match record_result(proof) {
+        Some(proof_bytes) => proof_bytes.into(),
+        None => ByteBuffer::null(),
+    }
+}
+
+#[no_mangle]
+/// Verifies a cryptographic proof for a sum operation with a loaded verifying key.
+///
+/// Returns `1` if the proof is valid for the public value `c`, `0` if it is not,
+/// and `-1` on error; the reason is available from `last_error_code` and
+/// `last_error_message`.
+///
+/// # Safety
+///
+/// - `vk` must be a handle returned by `load_verifying_key` that has not been freed.
+/// - `proof` must be a valid pointer to an array of bytes that represents the proof,
+///   with `proof_length` specifying the number of bytes in the array.
+pub unsafe extern "C" fn verify_proof_for_sum_with_key(
+    vk: *const VerifyingKeyHandle,
+    proof: *const c_uchar,
+    proof_length: usize,
+    c: u32,
+) -> c_int {
+    verification_status(|| {
+        let vk = handle_ref(vk)?;
+        let proof = convert_to_vec(proof, proof_length);
+        verify_with_key(vk, proof, &[c.into()], &sum_circuit()?)
+    })
+}
+
+#[no_mangle]
+/// Generates a cryptographic proof that a private string starts with a public prefix,
+/// with a loaded proving key.
+///
+/// Returns a null buffer on failure; the reason is available from `last_error_code`
+/// and `last_error_message`.
+///
+/// # Safety
+///
+/// - `pk` must be a handle returned by `load_proving_key` that has not been freed.
+/// - `prefix` and `full` must be valid pointers to arrays of `prefix_length` and
+///   `full_length` bytes respectively.
+/// - The returned buffer must be released with `free_byte_buffer`.
+pub unsafe extern "C" fn generate_proof_for_compare_with_key(
+    pk: *const ProvingKeyHandle,
+    prefix: *const c_uchar,
+    prefix_length: usize,
+    full: *const c_uchar,
+    full_length: usize,
+) -> ByteBuffer {
+    let proof = || {
+        let pk = handle_ref(pk)?;
+        let prefix = PrimeString::<Fr>::from(convert_to_vec(prefix, prefix_length).as_slice());
+        let full = PrimeString::<Fr>::from(convert_to_vec(full, full_length).as_slice());
+        prove_with_key(
+            pk,
+            CompareCircuit {
+                shorter: Some(prefix.into()),
+                larger: Some(full.into()),
+            },
+            &compare_circuit(prefix_length)?,
+        )
+    };
+
+    match record_result(proof) {
+        Some(proof_bytes) => proof_bytes.into(),
+        None => ByteBuffer::null(),
+    }
+}
+
+#[no_mangle]
+/// Verifies a cryptographic proof that a private string starts with a public prefix,
+/// with a loaded verifying key.
+///
+/// Returns `1` if the proof is valid for `prefix`, `0` if it is not, and `-1` on error;
+/// the reason is available from `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// - `vk` must be a handle returned by `load_verifying_key` that has not been freed.
+/// - `proof` must be a valid pointer to an array of bytes that represents the proof,
+///   with `proof_length` specifying the number of bytes in the array.
+/// - `prefix` must be a valid pointer to an array of `prefix_length` bytes.
+pub unsafe extern "C" fn verify_proof_for_compare_with_key(
+    vk: *const VerifyingKeyHandle,
+    proof: *const c_uchar,
+    proof_length: usize,
+    prefix: *const c_uchar,
+    prefix_length: usize,
+) -> c_int {
+    verification_status(|| {
+        let vk = handle_ref(vk)?;
+        let proof = convert_to_vec(proof, proof_length);
+        let prefix = PrimeString::<Fr>::from(convert_to_vec(prefix, prefix_length).as_slice());
+        verify_with_key(
+            vk,
+            proof,
+            &Vec::<Fr>::from(prefix),
+            &compare_circuit(prefix_length)?,
+        )
+    })
+}
+
+#[no_mangle]
+/// Starts generating a proof for a sum operation on a background thread.
+///
+/// `on_progress`, if not null, is called as each phase of the prover starts, and
+/// `on_complete` is called exactly once with the outcome. Both are called from the
+/// worker thread with `user_data` as their first argument.
+///
+/// Returns null if the job could not be started, in which case neither callback is
+/// called; the reason is available from `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// - `pk` must be a handle returned by `load_proving_key` that has not been freed.
+/// - `user_data` must remain valid, and be safe to use from another thread, until
+///   `on_complete` has been called.
+/// - The returned job must be released with `free_proving_job`.
+pub unsafe extern "C" fn generate_proof_for_sum_async(
+    pk: *const ProvingKeyHandle,
+    a: u32,
+    b: u32,
+    c: u32,
+    on_progress: ProgressCallback,
+    on_complete: CompletionCallback,
+    user_data: *mut c_void,
+) -> *mut ProvingJob {
+    proving_job_handle(|| {
+        let pk = handle_ref(pk)?;
+        spawn_proving_job(
+            pk,
+            SumCircuit::new(Some(a.into()), Some(b.into()), Some(c.into())),
+            sum_circuit()?,
+            on_progress,
+            on_complete,
+            user_data,
+        )
+    })
+}
+
+#[no_mangle]
+/// Starts generating a proof that a private string starts with a public prefix on a
+/// background thread.
+///
+/// The inputs are copied before this returns. `on_progress`, if not null, is called as
+/// each phase of the prover starts, and `on_complete` is called exactly once with the
+/// outcome. Both are called from the worker thread with `user_data` as their first
+/// argument.
+///
+/// Returns null if the job could not be started, in which case neither callback is
+/// called; the reason is available from `last_error_code` and `last_error_message`.
+///
+/// # Safety
+///
+/// - `pk` must be a handle returned by `load_proving_key` that has not been freed.
+/// - `prefix` and `full` must be valid pointers to arrays of `prefix_length` and
+///   `full_length` bytes respectively.
+/// - `user_data` must remain valid, and be safe to use from another thread, until
+///   `on_complete` has been called.
+/// - The returned job must be released with `free_proving_job`.
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn generate_proof_for_compare_async(
+    pk: *const ProvingKeyHandle,
+    prefix: *const c_uchar,
+    prefix_length: usize,
+    full: *const c_uchar,
+    full_length: usize,
+    on_progress: ProgressCallback,
+    on_complete: CompletionCallback,
+    user_data: *mut c_void,
+) -> *mut ProvingJob {
+    proving_job_handle(|| {
+        let pk = handle_ref(pk)?;
+        let prefix = PrimeString::<Fr>::from(convert_to_vec(prefix, prefix_length).as_slice());
+        let full = PrimeString::<Fr>::from(convert_to_vec(full, full_length).as_slice());
+        spawn_proving_job(
+            pk,
+            CompareCircuit {
+                shorter: Some(prefix.into()),
+                larger: Some(full.into()),
+            },
+            compare_circuit(prefix_length)?,
+            on_progress,
+            on_complete,
+            user_data,
+        )
+    })
+}
+
+#[no_mangle]
+/// Asks a proving job to stop.
+///
+/// The job stops when the next phase of the prover would start and then completes with
+/// `ErrorCode::Cancelled`. A job that has already finished is not affected.
+///
+/// # Safety
+///
+/// `job` must be a handle returned by one of the `_async` exports that has not been
+/// freed. Null handles are ignored.
+pub unsafe extern "C" fn cancel_proving_job(job: *const ProvingJob) {
+    if let Some(job) = job.as_ref() {
+        job.cancelled.store(true, Ordering::Release);
+    }
+}
+
+#[no_mangle]
+/// Waits for a proving job to complete and frees its handle.
+///
+/// When this returns, the completion callback has been called. Call
+/// `cancel_proving_job` first to avoid waiting for the whole proof. If called from one
+/// of the job's own callbacks, the handle is freed without waiting.
+///
+/// # Safety
+///
+/// `job` must have been returned by one of the `_async` exports and must not be used
+/// after this call. Null handles are ignored.
+pub unsafe extern "C" fn free_proving_job(job: *mut ProvingJob) {
+    if !job.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut job = Box::from_raw(job);
+            if let Some(worker) = job.worker.take() {
+                if worker.thread().id() != thread::current().id() {
+                    let _ = worker.join();
+                }
+            }
+        }));
+    }
+}
+
+#[no_mangle]
+/// Frees a buffer previously returned by one of the exports.
+///
+/// # Safety
+///
+/// `buffer` must have been returned by this library and not modified by the caller.
+/// This function should only be called once for any given buffer to avoid double-free
+/// errors. Buffers with a null `data` pointer are ignored.
+pub unsafe extern "C" fn free_byte_buffer(buffer: ByteBuffer) {
+    if !buffer.data.is_null() {
+        let _ = panic::catch_unwind(|| unsafe {
             // Recreate the Vec and let it drop
-            let _ = Vec::from_raw_parts(ptr, len as usize, capacity as usize);
-        }
+            let _ = Vec::from_raw_parts(buffer.data, buffer.len, buffer.capacity);
+        });
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::circuits::groth16::TrySerializer;
-
     use super::*;
+    use crate::circuits::container::Artifact;
+    use ark_ff::BigInteger;
 
-    fn convert_to_c(v: Vec<u8>) -> (*mut c_uchar, usize) {
-        let length = v.len() as usize;
-        let mut buffer: Vec<c_uchar> = v.into_iter().map(|x| x as c_uchar).collect();
-        let ptr = buffer.as_mut_ptr();
+    fn to_container<T: Artifact>(artifact: &T, circuit: &CircuitId) -> Vec<u8> {
+        container::to_bytes(artifact, circuit, SerializationMode::UNCOMPRESSED)
+            .expect("serialisation")
+    }
 
-        std::mem::forget(buffer);
+    #[test]
+    fn test_ffi_sum() {
+        let (pk, _) = crate::circuits::groth16::setup::<Bn254, _>(SumCircuit::default())
+            .expect("setup of keys");
+        let pk: ByteBuffer = to_container(
+            &pk,
+            &SumCircuit::<Fr>::circuit_id().expect("circuit identified"),
+        )
+        .into();
+        unsafe {
+            let proof = generate_proof_for_sum(pk.data, pk.len, 10, 20, 30);
+            assert!(!proof.data.is_null(), "we should have a proof");
+            assert!(proof.capacity >= proof.len);
+            let p = convert_to_vec(proof.data, proof.len);
+            let id = SumCircuit::<Fr>::circuit_id().expect("circuit identified");
+            assert!(proof_from_bytes(p, &id).is_ok(), "the proof should decode");
+            free_byte_buffer(proof);
+            free_byte_buffer(pk);
+        }
+    }
 
-        (ptr, length)
+    #[test]
+    fn test_ffi_verify_sum() {
+        let (pk, vk) = crate::circuits::groth16::setup::<Bn254, _>(SumCircuit::default())
+            .expect("setup of keys");
+        let proof = crate::circuits::groth16::generate_proof(
+            pk,
+            SumCircuit::new(Some(10.into()), Some(20.into()), Some(30.into())),
+        )
+        .expect("proof created");
+        let sum = SumCircuit::<Fr>::circuit_id().expect("circuit identified");
+        let vk = to_container(&vk, &sum);
+        let proof = to_container(proof.as_ref(), &sum);
+        unsafe {
+            assert_eq!(
+                verify_proof_for_sum(vk.as_ptr(), vk.len(), proof.as_ptr(), proof.len(), 30),
+                1
+            );
+            assert_eq!(
+                verify_proof_for_sum(vk.as_ptr(), vk.len(), proof.as_ptr(), proof.len(), 31),
+                0
+            );
+            assert_eq!(
+                verify_proof_for_sum(vk.as_ptr(), vk.len(), proof.as_ptr(), 0, 30),
+                -1
+            );
+            assert_eq!(last_error_code(), ErrorCode::InvalidProof);
+            assert!(!last_error_message().is_null());
+            assert_eq!(
+                verify_proof_for_sum(proof.as_ptr(), proof.len(), proof.as_ptr(), proof.len(), 30),
+                -1
+            );
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+        }
     }
 
     #[test]
-    fn test_ffi_sum() {
-        let (pk, _) =
-            crate::circuits::groth16::setup(SumCircuit::default()).expect("setup of keys");
-        let mut out_len: c_int = 0;
-        let out_len = &mut out_len;
-        let pk = pk.try_to_bytes().expect("serialisation");
-        let (pk, pk_length) = convert_to_c(pk);
+    fn test_ffi_setup_for_sum() {
+        let (mut pk, mut vk) = (ByteBuffer::null(), ByteBuffer::null());
         unsafe {
-            let proof = generate_proof_for_sum(pk, pk_length, 10, 20, 30, out_len);
-            assert!(proof != std::ptr::null_mut(), "we should have a proof");
-            let p = convert_to_vec(proof, *out_len as usize);
-            let p: String = p.iter().map(|b| format!("{:02x}", b)).collect();
-            println!("proof: {}", p);
-            Vec::from_raw_parts(proof, pk_length, pk_length);
-            Vec::from_raw_parts(pk, pk_length, pk_length);
+            let status = setup_for_sum(&mut pk, &mut vk);
+            assert_eq!(status, ErrorCode::Ok, "keys should be generated");
+
+            let proof = generate_proof_for_sum(pk.data, pk.len, 10, 20, 30);
+            assert!(!proof.data.is_null(), "we should have a proof");
+            assert_eq!(
+                verify_proof_for_sum(vk.data, vk.len, proof.data, proof.len, 30),
+                1
+            );
+
+            free_byte_buffer(proof);
+            free_byte_buffer(pk);
+            free_byte_buffer(vk);
+        }
+    }
+
+    #[test]
+    fn test_ffi_compare() {
+        let (mut pk, mut vk) = (ByteBuffer::null(), ByteBuffer::null());
+        let prefix = b"abc";
+        let full = b"abcdef";
+        unsafe {
+            let status = setup_for_compare(prefix.len(), &mut pk, &mut vk);
+            assert_eq!(status, ErrorCode::Ok, "keys should be generated");
+
+            let proof = generate_proof_for_compare(
+                pk.data,
+                pk.len,
+                prefix.as_ptr(),
+                prefix.len(),
+                full.as_ptr(),
+                full.len(),
+            );
+            assert!(!proof.data.is_null(), "we should have a proof");
+            assert_eq!(
+                verify_proof_for_compare(
+                    vk.data,
+                    vk.len,
+                    proof.data,
+                    proof.len,
+                    prefix.as_ptr(),
+                    prefix.len()
+                ),
+                1
+            );
+            assert_eq!(
+                verify_proof_for_compare(
+                    vk.data,
+                    vk.len,
+                    proof.data,
+                    proof.len,
+                    b"abd".as_ptr(),
+                    3
+                ),
+                0
+            );
+            assert_eq!(
+                verify_proof_for_compare(vk.data, vk.len, proof.data, proof.len, b"ab".as_ptr(), 2),
+                -1
+            );
+            // The keys are for prefixes of three bytes, so a shorter prefix is another circuit.
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+
+            free_byte_buffer(proof);
+            free_byte_buffer(pk);
+            free_byte_buffer(vk);
+        }
+    }
+
+    #[test]
+    fn test_ffi_compare_prefix_too_long() {
+        let (pk, _) = crate::circuits::groth16::setup::<Bn254, _>(
+            CompareCircuit::<Fr>::with_prefix_length(3),
+        )
+        .expect("setup of keys");
+        let pk = to_container(
+            &pk,
+            &CompareCircuit::<Fr>::circuit_id(3).expect("circuit identified"),
+        );
+        unsafe {
+            let proof = generate_proof_for_compare(
+                pk.as_ptr(),
+                pk.len(),
+                b"abc".as_ptr(),
+                3,
+                b"ab".as_ptr(),
+                2,
+            );
+            assert!(proof.data.is_null(), "we should not have a proof");
+            assert_eq!(last_error_code(), ErrorCode::InvalidWitness);
+            let message = std::ffi::CStr::from_ptr(last_error_message());
+            assert!(!message.to_bytes().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_ffi_bad_sum() {
+        let (pk, _) = crate::circuits::groth16::setup::<Bn254, _>(SumCircuit::default())
+            .expect("setup of keys");
+        let pk = to_container(
+            &pk,
+            &SumCircuit::<Fr>::circuit_id().expect("circuit identified"),
+        );
+        unsafe {
+            let proof = generate_proof_for_sum(pk.as_ptr(), pk.len(), 10, 31, 42);
+            assert!(proof.data.is_null(), "we should not have a proof");
+            assert_eq!(last_error_code(), ErrorCode::InvalidWitness);
+        }
+    }
+
+    #[test]
+    fn test_ffi_rejects_keys_for_other_circuits() {
+        let (mut pk, mut vk) = (ByteBuffer::null(), ByteBuffer::null());
+        let (mut sum_pk, mut sum_vk) = (ByteBuffer::null(), ByteBuffer::null());
+        unsafe {
+            assert_eq!(setup_for_compare(3, &mut pk, &mut vk), ErrorCode::Ok);
+            assert_eq!(setup_for_sum(&mut sum_pk, &mut sum_vk), ErrorCode::Ok);
+
+            let proof = generate_proof_for_sum(pk.data, pk.len, 10, 32, 42);
+            assert!(proof.data.is_null(), "a compare key can't prove a sum");
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+            let message = std::ffi::CStr::from_ptr(last_error_message());
+            let message = message.to_str().expect("UTF-8");
+            assert!(message.starts_with("the circuit is compare ("), "{message}");
+
+            // A compare key for a prefix of another length is another circuit too.
+            let proof = generate_proof_for_compare(
+                pk.data,
+                pk.len,
+                b"ab".as_ptr(),
+                2,
+                b"abcdef".as_ptr(),
+                6,
+            );
+            assert!(proof.data.is_null());
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+
+            let pk_handle = load_proving_key(pk.data, pk.len);
+            assert!(!pk_handle.is_null(), "the key is loaded for any circuit");
+            let proof = generate_proof_for_sum_with_key(pk_handle, 10, 32, 42);
+            assert!(proof.data.is_null());
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+            assert!(load_proving_key(vk.data, vk.len).is_null());
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+
+            // A valid sum proof is rejected by the compare verifying key, and vice versa.
+            let proof = generate_proof_for_sum(sum_pk.data, sum_pk.len, 10, 32, 42);
+            assert!(!proof.data.is_null(), "we should have a proof");
+            assert_eq!(
+                verify_proof_for_sum(vk.data, vk.len, proof.data, proof.len, 42),
+                -1
+            );
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+            assert_eq!(
+                verify_proof_for_compare(
+                    sum_vk.data,
+                    sum_vk.len,
+                    proof.data,
+                    proof.len,
+                    b"abc".as_ptr(),
+                    3
+                ),
+                -1
+            );
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+
+            free_byte_buffer(proof);
+            free_proving_key(pk_handle);
+            for buffer in [pk, vk, sum_pk, sum_vk] {
+                free_byte_buffer(buffer);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ffi_catches_panics() {
+        let result = record_result::<()>(|| panic!("boom"));
+        assert!(result.is_none());
+        assert_eq!(last_error_code(), ErrorCode::Panic);
+        let message = unsafe { std::ffi::CStr::from_ptr(last_error_message()) };
+        assert_eq!(message.to_str(), Ok("boom"));
+    }
+
+    #[test]
+    fn test_ffi_key_handles() {
+        let (mut pk, mut vk) = (ByteBuffer::null(), ByteBuffer::null());
+        unsafe {
+            assert_eq!(setup_for_sum(&mut pk, &mut vk), ErrorCode::Ok);
+            let pk_handle = load_proving_key(pk.data, pk.len);
+            let vk_handle = load_verifying_key(vk.data, vk.len);
+            assert!(!pk_handle.is_null() && !vk_handle.is_null());
+
+            for (a, b) in [(1, 2), (10, 20)] {
+                let proof = generate_proof_for_sum_with_key(pk_handle, a, b, a + b);
+                assert!(!proof.data.is_null(), "we should have a proof");
+                assert_eq!(
+                    verify_proof_for_sum_with_key(vk_handle, proof.data, proof.len, a + b),
+                    1
+                );
+                assert_eq!(
+                    verify_proof_for_sum_with_key(vk_handle, proof.data, proof.len, a + b + 1),
+                    0
+                );
+                free_byte_buffer(proof);
+            }
+
+            assert!(load_verifying_key(pk.data, 1).is_null());
+            assert_eq!(last_error_code(), ErrorCode::InvalidKey);
+            let proof = generate_proof_for_sum_with_key(std::ptr::null(), 1, 2, 3);
+            assert!(proof.data.is_null());
+            assert_eq!(last_error_code(), ErrorCode::NullPointer);
+
+            free_proving_key(pk_handle);
+            free_verifying_key(vk_handle);
+            free_byte_buffer(pk);
+            free_byte_buffer(vk);
+        }
+    }
+
+    /// What the callbacks of a proving job saw.
+    #[derive(Default)]
+    struct JobEvents {
+        entered: AtomicBool,
+        release: AtomicBool,
+        phases: std::sync::Mutex<Vec<ProvingPhase>>,
+        outcome: std::sync::Mutex<Option<(ErrorCode, Vec<u8>)>>,
+    }
+
+    extern "C" fn record_phase(user_data: *mut c_void, phase: ProvingPhase) {
+        let events = unsafe { &*(user_data as *const JobEvents) };
+        events.entered.store(true, Ordering::Release);
+        while !events.release.load(Ordering::Acquire) {
+            thread::yield_now();
+        }
+        events.phases.lock().unwrap().push(phase);
+    }
+
+    extern "C" fn record_outcome(
+        user_data: *mut c_void,
+        code: ErrorCode,
+        proof: ByteBuffer,
+        _message: *const c_char,
+    ) {
+        let events = unsafe { &*(user_data as *const JobEvents) };
+        let bytes = convert_to_vec(proof.data, proof.len);
+        unsafe { free_byte_buffer(proof) };
+        *events.outcome.lock().unwrap() = Some((code, bytes));
+    }
+
+    #[test]
+    fn test_ffi_async_proof() {
+        let (mut pk, mut vk) = (ByteBuffer::null(), ByteBuffer::null());
+        unsafe {
+            assert_eq!(setup_for_sum(&mut pk, &mut vk), ErrorCode::Ok);
+            let pk_handle = load_proving_key(pk.data, pk.len);
+
+            let events = JobEvents::default();
+            events.release.store(true, Ordering::Release);
+            let user_data = &events as *const JobEvents as *mut c_void;
+            let job = generate_proof_for_sum_async(
+                pk_handle,
+                10,
+                32,
+                42,
+                Some(record_phase),
+                Some(record_outcome),
+                user_data,
+            );
+            assert!(!job.is_null(), "the job should start");
+            // The job keeps its own reference to the key.
+            free_proving_key(pk_handle);
+            free_proving_job(job);
+
+            assert_eq!(events.phases.lock().unwrap().len(), 7);
+            let (code, proof) = events
+                .outcome
+                .lock()
+                .unwrap()
+                .take()
+                .expect("job completed");
+            assert_eq!(code, ErrorCode::Ok);
+            assert_eq!(
+                verify_proof_for_sum(vk.data, vk.len, proof.as_ptr(), proof.len(), 42),
+                1
+            );
+
+            free_byte_buffer(pk);
+            free_byte_buffer(vk);
+        }
+    }
+
+    #[test]
+    fn test_ffi_async_proof_cancelled() {
+        let (mut pk, mut vk) = (ByteBuffer::null(), ByteBuffer::null());
+        unsafe {
+            assert_eq!(setup_for_sum(&mut pk, &mut vk), ErrorCode::Ok);
+            let pk_handle = load_proving_key(pk.data, pk.len);
+
+            // The first progress callback blocks until the job has been cancelled.
+            let events = JobEvents::default();
+            let user_data = &events as *const JobEvents as *mut c_void;
+            let job = generate_proof_for_sum_async(
+                pk_handle,
+                10,
+                32,
+                42,
+                Some(record_phase),
+                Some(record_outcome),
+                user_data,
+            );
+            assert!(!job.is_null(), "the job should start");
+            while !events.entered.load(Ordering::Acquire) {
+                thread::yield_now();
+            }
+            cancel_proving_job(job);
+            events.release.store(true, Ordering::Release);
+            free_proving_job(job);
+
+            assert_eq!(*events.phases.lock().unwrap(), [ProvingPhase::Synthesis]);
+            let (code, proof) = events
+                .outcome
+                .lock()
+                .unwrap()
+                .take()
+                .expect("job completed");
+            assert_eq!(code, ErrorCode::Cancelled);
+            assert!(proof.is_empty());
+
+            let job = generate_proof_for_sum_async(pk_handle, 10, 32, 42, None, None, user_data);
+            assert!(job.is_null());
+            assert_eq!(last_error_code(), ErrorCode::NullPointer);
+
+            free_proving_key(pk_handle);
+            free_byte_buffer(pk);
+            free_byte_buffer(vk);
+        }
+    }
+
+    #[test]
+    fn test_ffi_abi_version() {
+        assert_eq!(TRIVIAL_CIRCUITS_ABI_VERSION, 2);
+        assert_eq!(trivial_circuits_abi_version(), TRIVIAL_CIRCUITS_ABI_VERSION);
+    }
+
+    fn bigint_to_bytes(value: BigInt<4>) -> [u8; FIELD_ELEMENT_LENGTH] {
+        let mut bytes = [0u8; FIELD_ELEMENT_LENGTH];
+        for (chunk, limb) in bytes.chunks_mut(8).zip(value.0) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_ffi_wide_sum() {
+        let (mut pk, mut vk) = (ByteBuffer::null(), ByteBuffer::null());
+        unsafe {
+            assert_eq!(setup_for_sum(&mut pk, &mut vk), ErrorCode::Ok);
+
+            // The sum of two `u64::MAX` needs 65 bits, so the `u64` variant can only be
+            // given a wrong `c`, which is rejected as a witness that doesn't add up.
+            let (a, b) = (u64::MAX, u64::MAX);
+            let proof = generate_proof_for_sum_u64(pk.data, pk.len, a, b, 0);
+            assert!(proof.data.is_null(), "0 is not the sum of a and b");
+            assert_eq!(last_error_code(), ErrorCode::InvalidWitness);
+
+            // The field element variant takes the full sum.
+            let c = Fr::from(a) + Fr::from(b);
+            assert_eq!(c.into_bigint().num_bits(), 65);
+            let to_bytes = |value: Fr| bigint_to_bytes(value.into_bigint());
+            let (a, b, c) = (to_bytes(a.into()), to_bytes(b.into()), to_bytes(c));
+            let proof =
+                generate_proof_for_sum_bytes(pk.data, pk.len, a.as_ptr(), b.as_ptr(), c.as_ptr());
+            assert!(!proof.data.is_null(), "we should have a proof");
+            assert_eq!(
+                verify_proof_for_sum_bytes(vk.data, vk.len, proof.data, proof.len, c.as_ptr()),
+                1
+            );
+            free_byte_buffer(proof);
+
+            let proof = generate_proof_for_sum_u64(pk.data, pk.len, 1 << 40, 1 << 40, 1 << 41);
+            assert!(!proof.data.is_null(), "we should have a proof");
+            assert_eq!(
+                verify_proof_for_sum_u64(vk.data, vk.len, proof.data, proof.len, 1 << 41),
+                1
+            );
+            free_byte_buffer(proof);
+
+            let modulus = bigint_to_bytes(Fr::MODULUS);
+            let zero = [0u8; FIELD_ELEMENT_LENGTH];
+            let proof = generate_proof_for_sum_bytes(
+                pk.data,
+                pk.len,
+                modulus.as_ptr(),
+                zero.as_ptr(),
+                zero.as_ptr(),
+            );
+            assert!(proof.data.is_null(), "the modulus is not a canonical value");
+            assert_eq!(last_error_code(), ErrorCode::InvalidInput);
+
+            free_byte_buffer(pk);
+            free_byte_buffer(vk);
         }
     }
 }
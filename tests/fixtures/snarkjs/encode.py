@@ -0,0 +1,412 @@
+#!/usr/bin/env python3
+"""Writes the snarkjs fixtures in this directory without snarkjs.
+
+This is a stand-in for generate.sh, which needs Node.js and network access. It encodes
+the same files from the snarkjs formats with exact integer arithmetic on BN254, sharing
+no code with the crate, so the tests check the crate against an independent encoding:
+
+* pot4_0001.ptau: powers of tau for a domain of 2^4 elements, as after one
+  contribution. The contribution record in section 7 has the snarkjs layout, but its
+  hashes are zeros, so `snarkjs powersoftau verify` rejects the file.
+* verification_key.json, proof.json and public.json: a key and a proof for the public
+  input 33. They are made with the secrets of the key rather than from a circuit, which
+  verifiers can't tell apart. `vk_alphabeta_12` is computed with the optimal ate pairing
+  and the final exponentiation of libff, which snarkjs and arkworks both follow.
+
+The secrets come from a fixed seed, so running this again writes the same files.
+"""
+
+import json
+import pathlib
+import random
+import struct
+
+# The base field modulus, the group order and the parameter of BN254.
+Q = 21888242871839275222246405745257275088696311157297823662689037894645226208583
+R = 21888242871839275222246405745257275088548364400416034343698204186575808495617
+X = 4965661367192848881
+
+POWER = 4
+SEED = 20240615
+PUBLIC_INPUT = 33
+
+
+class Fq2:
+    """An element c0 + c1 * u of Fq[u] / (u^2 + 1)."""
+
+    def __init__(self, c0, c1=0):
+        self.c0, self.c1 = c0 % Q, c1 % Q
+
+    def __add__(self, other):
+        return Fq2(self.c0 + other.c0, self.c1 + other.c1)
+
+    def __sub__(self, other):
+        return Fq2(self.c0 - other.c0, self.c1 - other.c1)
+
+    def __neg__(self):
+        return Fq2(-self.c0, -self.c1)
+
+    def __mul__(self, other):
+        if isinstance(other, int):
+            return Fq2(self.c0 * other, self.c1 * other)
+        return Fq2(
+            self.c0 * other.c0 - self.c1 * other.c1,
+            self.c0 * other.c1 + self.c1 * other.c0,
+        )
+
+    def __eq__(self, other):
+        return (self.c0, self.c1) == (other.c0, other.c1)
+
+    def inverse(self):
+        norm = pow(self.c0 * self.c0 + self.c1 * self.c1, Q - 2, Q)
+        return Fq2(self.c0 * norm, -self.c1 * norm)
+
+    def conjugate(self):
+        return Fq2(self.c0, -self.c1)
+
+    def is_zero(self):
+        return self.c0 == 0 and self.c1 == 0
+
+    def pow(self, e):
+        result, base = Fq2(1), self
+        while e:
+            if e & 1:
+                result = result * base
+            base = base * base
+            e >>= 1
+        return result
+
+
+class Fq:
+    """An element of the base field, with the interface of Fq2 that the curves use."""
+
+    def __init__(self, value):
+        self.value = value % Q
+
+    def __add__(self, other):
+        return Fq(self.value + other.value)
+
+    def __sub__(self, other):
+        return Fq(self.value - other.value)
+
+    def __neg__(self):
+        return Fq(-self.value)
+
+    def __mul__(self, other):
+        if isinstance(other, int):
+            return Fq(self.value * other)
+        return Fq(self.value * other.value)
+
+    def __eq__(self, other):
+        return self.value == other.value
+
+    def inverse(self):
+        return Fq(pow(self.value, Q - 2, Q))
+
+    def is_zero(self):
+        return self.value == 0
+
+
+# Points are affine (x, y) pairs, with None for the point at infinity. Scalar
+# multiplication uses Jacobian coordinates to avoid an inversion per step.
+G1 = (Fq(1), Fq(2))
+G2 = (
+    Fq2(
+        10857046999023057135944570762232829481370756359578518086990519993285655852781,
+        11559732032986387107991004021392285783925812861821192530917403151452391805634,
+    ),
+    Fq2(
+        8495653923123431417604973247489272438418190587263600148770280649306958101930,
+        4082367875863433681332203403145435568316851327593401208105741076214120093531,
+    ),
+)
+
+
+def jacobian_double(p):
+    x, y, z = p
+    if y.is_zero():
+        return None
+    a = x * x
+    b = y * y
+    c = b * b
+    d = ((x + b) * (x + b) - a - c) * 2
+    e = a * 3
+    x3 = e * e - d * 2
+    return (x3, e * (d - x3) - c * 8, y * z * 2)
+
+
+def jacobian_add(p, q):
+    if p is None:
+        return q
+    if q is None:
+        return p
+    x1, y1, z1 = p
+    x2, y2, z2 = q
+    z1z1, z2z2 = z1 * z1, z2 * z2
+    u1, u2 = x1 * z2z2, x2 * z1z1
+    s1, s2 = y1 * z2 * z2z2, y2 * z1 * z1z1
+    if u1 == u2:
+        return jacobian_double(p) if s1 == s2 else None
+    h = u2 - u1
+    i = (h * 2) * (h * 2)
+    j = h * i
+    r = (s2 - s1) * 2
+    v = u1 * i
+    x3 = r * r - j - v * 2
+    y3 = r * (v - x3) - s1 * j * 2
+    return (x3, y3, ((z1 + z2) * (z1 + z2) - z1z1 - z2z2) * h)
+
+
+def multiply(point, scalar):
+    one = point[0] * 0 + point[0].__class__(1)
+    result, base = None, (point[0], point[1], one)
+    scalar %= R
+    while scalar:
+        if scalar & 1:
+            result = jacobian_add(result, base)
+        base = jacobian_double(base)
+        scalar >>= 1
+    if result is None:
+        return None
+    x, y, z = result
+    z_inverse = z.inverse()
+    z2 = z_inverse * z_inverse
+    return (x * z2, y * z2 * z_inverse)
+
+
+def add(p, q):
+    one = lambda point: (point[0], point[1], point[0] * 0 + point[0].__class__(1))
+    if p is None:
+        return q
+    if q is None:
+        return p
+    result = jacobian_add(one(p), one(q))
+    if result is None:
+        return None
+    x, y, z = result
+    z_inverse = z.inverse()
+    z2 = z_inverse * z_inverse
+    return (x * z2, y * z2 * z_inverse)
+
+
+# Fq12 elements are lists of 12 coefficients of powers of w, modulo w^12 = 18 w^6 - 82.
+# This basis is related to the tower Fq2[v] / (v^3 - (9 + u)), Fq6[w] / (w^2 - v) of
+# snarkjs and arkworks by v = w^2 and u = w^6 - 9.
+def fq12_mul(a, b):
+    product = [0] * 23
+    for i, ai in enumerate(a):
+        if ai:
+            for j, bj in enumerate(b):
+                product[i + j] += ai * bj
+    for i in range(22, 11, -1):
+        top = product[i]
+        product[i - 6] += 18 * top
+        product[i - 12] -= 82 * top
+    return [c % Q for c in product[:12]]
+
+
+def fq12_pow(a, e):
+    result = [1] + [0] * 11
+    while e:
+        if e & 1:
+            result = fq12_mul(result, a)
+        a = fq12_mul(a, a)
+        e >>= 1
+    return result
+
+
+def fq12_from_fq2(x, power):
+    """Embeds x * w^power for x in Fq2 and power < 6."""
+    coefficients = [0] * 12
+    coefficients[power] = (x.c0 - 9 * x.c1) % Q
+    coefficients[power + 6] = x.c1
+    return coefficients
+
+
+def fq12_add(*terms):
+    return [sum(c) % Q for c in zip(*terms)]
+
+
+def fq12_to_tower(a):
+    """The snarkjs layout [[c0.c0, c0.c1, c0.c2], [c1.c0, c1.c1, c1.c2]] of Fq2 pairs."""
+    coefficient = lambda e: [(a[e] + 9 * a[e + 6]) % Q, a[e + 6]]
+    return [[coefficient(e) for e in (0, 2, 4)], [coefficient(e) for e in (1, 3, 5)]]
+
+
+# The pairing works on G2 points of the twist, which w untwists as (x w^2, y w^3).
+XI = Fq2(9, 1)
+FROBENIUS_X = XI.pow((Q - 1) // 3)
+FROBENIUS_Y = XI.pow((Q - 1) // 2)
+
+
+def frobenius(point):
+    x, y = point
+    return (x.conjugate() * FROBENIUS_X, y.conjugate() * FROBENIUS_Y)
+
+
+def line(t, s, p):
+    """The line through the untwisted t and s, or the tangent at t, evaluated at p.
+
+    Vertical lines have values in Fq6, which the final exponentiation maps to one, so
+    they are left out.
+    """
+    (xt, yt), (xs, ys) = t, s
+    if xt == xs and not yt == ys:
+        return [1] + [0] * 11
+    if xt == xs:
+        slope = (xt * xt * 3) * (yt * 2).inverse()
+    else:
+        slope = (ys - yt) * (xs - xt).inverse()
+    xp, yp = p
+    return fq12_add(
+        [yp.value] + [0] * 11,
+        fq12_from_fq2(-(slope * xp.value), 1),
+        fq12_from_fq2(slope * xt - yt, 3),
+    )
+
+
+def pairing(p, q):
+    """The optimal ate pairing e(p, q), as libff, snarkjs and arkworks compute it."""
+    loop = 6 * X + 2
+    f, t = [1] + [0] * 11, q
+    for i in range(loop.bit_length() - 2, -1, -1):
+        f = fq12_mul(fq12_mul(f, f), line(t, t, p))
+        t = add(t, t)
+        if loop >> i & 1:
+            f = fq12_mul(f, line(t, q, p))
+            t = add(t, q)
+    q1 = frobenius(q)
+    q2 = frobenius(q1)
+    q2 = (q2[0], -q2[1])
+    f = fq12_mul(f, line(t, q1, p))
+    t = add(t, q1)
+    f = fq12_mul(f, line(t, q2, p))
+    # libff computes the hard part of the final exponentiation with the chain of
+    # Fuentes-Castaneda et al., which raises the exact pairing to 2x(6x^2 + 3x + 1).
+    return fq12_pow(f, (Q**12 - 1) // R * (2 * X * (6 * X * X + 3 * X + 1)))
+
+
+def montgomery(x):
+    return (x * 2**256 % Q).to_bytes(32, "little")
+
+
+def g1_bytes(point):
+    if point is None:
+        return bytes(64)
+    return montgomery(point[0].value) + montgomery(point[1].value)
+
+
+def g2_bytes(point):
+    x, y = point
+    return b"".join(montgomery(c) for c in (x.c0, x.c1, y.c0, y.c1))
+
+
+def section(kind, body):
+    return struct.pack("<IQ", kind, len(body)) + body
+
+
+def ptau(rng):
+    n = 2**POWER
+    tau, alpha, beta = (rng.randrange(1, R) for _ in range(3))
+    tau_powers = [pow(tau, i, R) for i in range(2 * n - 1)]
+    tau_g1 = [multiply(G1, t) for t in tau_powers]
+    tau_g2 = [multiply(G2, t) for t in tau_powers[:n]]
+    alpha_tau_g1 = [multiply(G1, alpha * t) for t in tau_powers[:n]]
+    beta_tau_g1 = [multiply(G1, beta * t) for t in tau_powers[:n]]
+    beta_g2 = multiply(G2, beta)
+
+    # The public key of the contribution: for each secret, g1_s, g1_s * secret and
+    # g2_sp * secret. snarkjs derives g2_sp from the transcript; nothing here checks it.
+    g1_keys, g2_keys = b"", b""
+    for secret in (tau, alpha, beta):
+        s, sp = rng.randrange(1, R), rng.randrange(1, R)
+        g1_keys += g1_bytes(multiply(G1, s)) + g1_bytes(multiply(G1, s * secret))
+        g2_keys += g2_bytes(multiply(G2, sp * secret))
+    name = b"trivial-circuits fixture"
+    contribution = (
+        g1_keys
+        + g2_keys
+        + g1_bytes(tau_g1[1])
+        + g2_bytes(tau_g2[1])
+        + g1_bytes(alpha_tau_g1[0])
+        + g1_bytes(beta_tau_g1[0])
+        + g2_bytes(beta_g2)
+        + bytes(216)  # The partial hash.
+        + bytes(64)  # The next challenge.
+        + struct.pack("<I", 0)  # A contribution rather than a beacon.
+        + struct.pack("<I", 2 + len(name))
+        + bytes([1, len(name)])
+        + name
+    )
+
+    sections = [
+        section(1, struct.pack("<I", 32) + Q.to_bytes(32, "little") + struct.pack("<II", POWER, POWER)),
+        section(2, b"".join(g1_bytes(p) for p in tau_g1)),
+        section(3, b"".join(g2_bytes(p) for p in tau_g2)),
+        section(4, b"".join(g1_bytes(p) for p in alpha_tau_g1)),
+        section(5, b"".join(g1_bytes(p) for p in beta_tau_g1)),
+        section(6, g2_bytes(beta_g2)),
+        section(7, struct.pack("<I", 1) + contribution),
+    ]
+    return b"ptau" + struct.pack("<II", 1, len(sections)) + b"".join(sections)
+
+
+def g1_json(point):
+    return [str(point[0].value), str(point[1].value), "1"]
+
+
+def g2_json(point):
+    x, y = point
+    return [[str(x.c0), str(x.c1)], [str(y.c0), str(y.c1)], ["1", "0"]]
+
+
+def groth16(rng):
+    """A verifying key and a proof for PUBLIC_INPUT, made with the secrets of the key.
+
+    A proof (a G1, b G2, c G1) verifies when ab = alpha beta + ic gamma + c delta for the
+    combination ic of the input commitments, so c follows from the other secrets.
+    """
+    alpha, beta, gamma, delta, a, b = (rng.randrange(1, R) for _ in range(6))
+    ic = [rng.randrange(1, R) for _ in range(2)]
+    inputs = ic[0] + ic[1] * PUBLIC_INPUT
+    c = (a * b - alpha * beta - inputs * gamma) * pow(delta, R - 2, R) % R
+
+    alpha_g1, beta_g2 = multiply(G1, alpha), multiply(G2, beta)
+    vk = {
+        "protocol": "groth16",
+        "curve": "bn128",
+        "nPublic": 1,
+        "vk_alpha_1": g1_json(alpha_g1),
+        "vk_beta_2": g2_json(beta_g2),
+        "vk_gamma_2": g2_json(multiply(G2, gamma)),
+        "vk_delta_2": g2_json(multiply(G2, delta)),
+        "vk_alphabeta_12": [
+            [[str(c) for c in pair] for pair in fq6]
+            for fq6 in fq12_to_tower(pairing(alpha_g1, beta_g2))
+        ],
+        "IC": [g1_json(multiply(G1, scalar)) for scalar in ic],
+    }
+    proof = {
+        "pi_a": g1_json(multiply(G1, a)),
+        "pi_b": g2_json(multiply(G2, b)),
+        "pi_c": g1_json(multiply(G1, c)),
+        "protocol": "groth16",
+        "curve": "bn128",
+    }
+    return vk, proof
+
+
+def main():
+    directory = pathlib.Path(__file__).parent
+    rng = random.Random(SEED)
+    (directory / f"pot{POWER}_0001.ptau").write_bytes(ptau(rng))
+    vk, proof = groth16(rng)
+    # snarkjs indents its JSON by one space.
+    write = lambda name, value: (directory / name).write_text(json.dumps(value, indent=1))
+    write("verification_key.json", vk)
+    write("proof.json", proof)
+    write("public.json", [str(PUBLIC_INPUT)])
+
+
+if __name__ == "__main__":
+    main()
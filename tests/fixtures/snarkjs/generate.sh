@@ -0,0 +1,42 @@
+#!/usr/bin/env bash
+# Regenerates the snarkjs fixtures in this directory with snarkjs itself: pot4_0001.ptau,
+# proof.json, verification_key.json and public.json. Needs Node.js and network access for
+# npx; encode.py writes files in the same formats without them. Check them with
+#
+#     cargo test snarkjs
+set -euo pipefail
+cd "$(dirname "$0")"
+
+snarkjs() { npx --yes snarkjs@0.7.5 "$@"; }
+
+# A powers-of-tau file for a domain of 2^4 elements with one contribution.
+snarkjs powersoftau new bn128 4 pot4_0000.ptau
+snarkjs powersoftau contribute pot4_0000.ptau pot4_0001.ptau \
+    --name="trivial-circuits fixture" -e="trivial-circuits fixture entropy"
+rm pot4_0000.ptau
+
+# A proof for a circuit proving knowledge of factors a = 3 and b = 11 of the public
+# c = 33, with its verifying key and public inputs.
+cat > multiplier.circom <<'CIRCOM'
+pragma circom 2.0.0;
+
+template Multiplier() {
+    signal input a;
+    signal input b;
+    signal output c;
+    c <== a * b;
+}
+
+component main = Multiplier();
+CIRCOM
+npx --yes circom2 multiplier.circom --r1cs --wasm
+snarkjs powersoftau prepare phase2 pot4_0001.ptau pot4_final.ptau
+snarkjs groth16 setup multiplier.r1cs pot4_final.ptau multiplier_0000.zkey
+snarkjs zkey contribute multiplier_0000.zkey multiplier.zkey \
+    --name="trivial-circuits fixture" -e="trivial-circuits fixture entropy"
+snarkjs zkey export verificationkey multiplier.zkey verification_key.json
+echo '{"a": "3", "b": "11"}' > input.json
+snarkjs wtns calculate multiplier_js/multiplier.wasm input.json witness.wtns
+snarkjs groth16 prove multiplier.zkey witness.wtns proof.json public.json
+rm -r multiplier.circom multiplier.r1cs multiplier_js multiplier_0000.zkey multiplier.zkey \
+    pot4_final.ptau input.json witness.wtns
@@ -0,0 +1,18 @@
+#!/usr/bin/env bash
+# Rewrites the verifier contracts for the keys of the Solidity tests, sum.sol and
+# compare.sol, and compiles them to sum.bin and compare.bin with the solc on PATH, or
+# the one SOLC points to. The EVM tests deploy the bytecode; run them with
+#
+#     cargo test -- --ignored evm
+set -euo pipefail
+cd "$(dirname "$0")"
+solc="${SOLC:-solc}"
+
+UPDATE_FIXTURES=1 cargo test --lib solidity::tests::contract_source_is_pinned
+
+out="$(mktemp -d)"
+trap 'rm -r "$out"' EXIT
+for name in sum compare; do
+    "$solc" --optimize --bin --output-dir "$out/$name" "$name.sol"
+    cp "$out/$name/Groth16Verifier.bin" "$name.bin"
+done
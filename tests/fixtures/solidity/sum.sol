@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+/// @title Groth16 verifier
+/// @notice Verifies BN254 Groth16 proofs for a single verifying key.
+/// @dev Generated by trivial-circuits. Do not edit.
+contract Groth16Verifier {
+    // Scalar field modulus
+    uint256 constant R = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    // Base field modulus
+    uint256 constant Q = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+
+    // Verifying key. G2 coordinates are split into c1 and c0.
+    uint256 constant ALPHA_X = 1096574201125597367295237391415289896587892650118792280533669490919315375152;
+    uint256 constant ALPHA_Y = 12798019686911297566198504120115116784666222273096368710224483440064491059819;
+    uint256 constant BETA_X1 = 11020515625719249797773964012198147565059165365007035430035008073155449381101;
+    uint256 constant BETA_X0 = 7843194650635028174306954474259666762220696283485009236664497881787869755932;
+    uint256 constant BETA_Y1 = 17229609981755210682211440646198238589283899684688518897433374459044293205491;
+    uint256 constant BETA_Y0 = 5641295038640557579464767026549315762220233971493145623605991363963238188193;
+    uint256 constant GAMMA_X1 = 14369782547448103774707652734980309037829115908919093197439549655057843906382;
+    uint256 constant GAMMA_X0 = 492488341548860346771007844880674319300255788101725592094699817361874950575;
+    uint256 constant GAMMA_Y1 = 11238951812251885414516588534931080982549213684106488432776961549550790277239;
+    uint256 constant GAMMA_Y0 = 17095729100860915550174206171462152561982315582951550210701823380790055987561;
+    uint256 constant DELTA_X1 = 12288236825007561571231819132435583098939893664324603973375891944597116071029;
+    uint256 constant DELTA_X0 = 767922557453609299444431898131864556034231164874971052665003409876978533712;
+    uint256 constant DELTA_Y1 = 10129088675187425723845169566171716376776034682773556064789740656423248219544;
+    uint256 constant DELTA_Y0 = 17902694200207971880351128801377131061639356003114436412564773631233267485780;
+    uint256 constant IC0_X = 13812032908224245022750344365048173585211890016776730231320943533956499621133;
+    uint256 constant IC0_Y = 6136307486975623598769658631877026831745367764833174022624817019356923239058;
+    uint256 constant IC1_X = 14325046749427400866898135425856077017858102049477834825930208475333737819197;
+    uint256 constant IC1_Y = 7742108317169592931540324721938314625993409289588097345663431275290678226046;
+
+    /// @notice Returns whether `(a, b, c)` is a valid proof for the public inputs.
+    /// @dev `b` is `[[x.c1, x.c0], [y.c1, y.c0]]`.
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[1] calldata input
+    ) public view returns (bool) {
+        if (a[0] >= Q || a[1] >= Q) {
+            return false;
+        }
+        for (uint256 i = 0; i < 1; i++) {
+            if (input[i] >= R) {
+                return false;
+            }
+        }
+
+        uint256[2] memory x = [IC0_X, IC0_Y];
+        x = ecAdd(x, ecMul([IC1_X, IC1_Y], input[0]));
+
+        // e(-A, B) * e(alpha, beta) * e(x, gamma) * e(C, delta) == 1
+        uint256[24] memory p;
+        p[0] = a[0];
+        p[1] = (Q - a[1]) % Q;
+        p[2] = b[0][0];
+        p[3] = b[0][1];
+        p[4] = b[1][0];
+        p[5] = b[1][1];
+        p[6] = ALPHA_X;
+        p[7] = ALPHA_Y;
+        p[8] = BETA_X1;
+        p[9] = BETA_X0;
+        p[10] = BETA_Y1;
+        p[11] = BETA_Y0;
+        p[12] = x[0];
+        p[13] = x[1];
+        p[14] = GAMMA_X1;
+        p[15] = GAMMA_X0;
+        p[16] = GAMMA_Y1;
+        p[17] = GAMMA_Y0;
+        p[18] = c[0];
+        p[19] = c[1];
+        p[20] = DELTA_X1;
+        p[21] = DELTA_X0;
+        p[22] = DELTA_Y1;
+        p[23] = DELTA_Y0;
+        uint256[1] memory out;
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 8, p, 768, out, 32)
+        }
+        return ok && out[0] == 1;
+    }
+
+    function ecAdd(uint256[2] memory p1, uint256[2] memory p2)
+        internal
+        view
+        returns (uint256[2] memory r)
+    {
+        uint256[4] memory args = [p1[0], p1[1], p2[0], p2[1]];
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 6, args, 128, r, 64)
+        }
+        require(ok, "ecAdd failed");
+    }
+
+    function ecMul(uint256[2] memory p, uint256 scalar)
+        internal
+        view
+        returns (uint256[2] memory r)
+    {
+        uint256[3] memory args = [p[0], p[1], scalar];
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 7, args, 96, r, 64)
+        }
+        require(ok, "ecMul failed");
+    }
+}